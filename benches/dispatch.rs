@@ -0,0 +1,76 @@
+//! Compares dispatching opcodes through a `HashMap`, as the CPU used to,
+//! with indexing the dense instruction table.
+//!
+//! The old map was built once behind a `lazy_static` and looked up with `get` on
+//! every fetch, which the `HashMap` side reproduces with a `OnceLock`. Its values
+//! were an `InstructionAccess` enum telling the functions taking only the CPU from
+//! those also taking the memory. Those functions are gone, so the map holds the
+//! current instruction functions instead, and as they all take the memory, the
+//! match on the enum is left out. Both sides run the exact same instructions:
+//! only the lookup differs.
+//!
+//! Run with `cargo bench`. `cargo test --all-targets` runs it too, without the
+//! `--bench` flag, in which case every opcode is only executed once as a smoke test.
+
+use std::collections::HashMap;
+use std::env;
+use std::hint::black_box;
+use std::sync::OnceLock;
+use std::time::{ Duration, Instant };
+
+use disco_gb::cpu::Cpu;
+use disco_gb::cpu::instructions::lookup::{ Instruction, INSTRS };
+use disco_gb::memory::Memory;
+
+const ROUNDS: usize = 20_000;
+
+/// The map the CPU used to look up instructions in, built on first use.
+fn hashed_instrs() -> &'static HashMap<u8, Instruction> {
+    static MAP: OnceLock<HashMap<u8, Instruction>> = OnceLock::new();
+    MAP.get_or_init(|| {
+        INSTRS.iter()
+            .enumerate()
+            .map(|(opcode, instr)| (opcode as u8, *instr))
+            .collect()
+    })
+}
+
+/// The register to register loads and ALU instructions.
+/// None of them touch the PC, so they can be executed over and over again.
+fn opcodes() -> Vec<u8> {
+    (0x40..=0xbf).filter(|&opcode| opcode != 0x76).collect()
+}
+
+/// Executes every opcode `rounds` times, looking up each instruction with `lookup`.
+/// The instruction register is set to the opcode first, as a fetch does.
+fn run(name: &str, rounds: usize, lookup: impl Fn(u8) -> Instruction) -> Duration {
+    let opcodes = opcodes();
+    let mut cpu = Cpu::new();
+    let mut memory = Memory::new();
+
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for &opcode in &opcodes {
+            let opcode = black_box(opcode);
+            cpu.set_ir(opcode);
+            black_box(lookup(opcode)(&mut cpu, &mut memory));
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let executed = (rounds * opcodes.len()) as f64;
+    println!(
+        "{:>8}: {:>10.2?} total, {:>6.2} ns/instruction",
+        name, elapsed, elapsed.as_nanos() as f64 / executed,
+    );
+    elapsed
+}
+
+fn main() {
+    let rounds = if env::args().any(|arg| arg == "--bench") { ROUNDS } else { 1 };
+
+    let hashed = run("HashMap", rounds, |opcode| hashed_instrs()[&opcode]);
+    let dense = run("table", rounds, |opcode| INSTRS[opcode as usize]);
+
+    println!("speedup: {:.2}x", hashed.as_secs_f64() / dense.as_secs_f64());
+}
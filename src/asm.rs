@@ -0,0 +1,386 @@
+//! A tiny SM83 assembler, mostly for writing tests as short programs.
+//!
+//! It accepts one instruction per line in the syntax of RGBDS, or of the
+//! disassembler: `[hl]` or `(hl)`, `ldh [$47], a`, `ld [hl+], a`, `cp $34` or
+//! `cp a, $34`, with numbers in hex (`$ff`), binary (`%1010`) or decimal.
+//! Labels end with a colon, `db` emits raw bytes and `;` starts a comment.
+//!
+//! ```
+//! use disco_gb::asm;
+//!
+//! let bytes = asm::assemble(0x0150, "
+//!     ld b, 3
+//! loop:
+//!     dec b
+//!     jr nz, loop
+//! ").unwrap();
+//! assert_eq!(bytes, [0x06, 0x03, 0x05, 0x20, 0xfd]);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::cpu::instructions::metadata::{ METADATA, METADATA_PREFIX };
+use crate::memory::Memory;
+
+/// The names that can't be labels, as they are operands of their own.
+const RESERVED: [&str; 14] = [
+    "a", "b", "c", "d", "e", "h", "l", "af", "bc", "de", "hl", "sp", "nz", "nc",
+];
+
+/// The instructions that may leave out their `a, ` operand.
+const ALU: [&str; 8] = ["add", "adc", "sub", "sbc", "and", "xor", "or", "cp"];
+
+/// An error assembling a line of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// The line, counting from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// A line of the source, without its label and comment.
+enum Statement {
+    /// An instruction: its opcode(s), its immediate operand if any, and its length.
+    Instruction { opcode: Vec<u8>, operand: Option<(Immediate, String)>, length: u16 },
+    /// Raw bytes, which may be labels or numbers.
+    Data(Vec<String>),
+}
+
+/// The kind of immediate operand in a template.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Immediate {
+    U8,
+    U16,
+    /// A signed offset, or the target of a relative jump.
+    I8,
+}
+
+/// Assembles `source`, the first byte of which is located at `origin`.
+pub fn assemble(origin: u16, source: &str) -> Result<Vec<u8>, AsmError> {
+    // The first pass finds the length of every statement, and so the labels' addresses.
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut addr = origin;
+    for (i, line) in source.lines().enumerate() {
+        let error = |message: String| AsmError { line: i + 1, message };
+        let mut code = line.split(';').next().unwrap().trim();
+
+        if let Some((label, rest)) = code.split_once(':') {
+            let label = label.trim().to_lowercase();
+            if !is_identifier(&label) {
+                return Err(error(format!("invalid label `{}`", label)));
+            }
+            if labels.insert(label.clone(), addr).is_some() {
+                return Err(error(format!("duplicate label `{}`", label)));
+            }
+            code = rest.trim();
+        }
+        if code.is_empty() {
+            continue;
+        }
+
+        let statement = parse(code).map_err(error)?;
+        addr = addr.wrapping_add(match &statement {
+            Statement::Instruction { length, .. } => *length,
+            Statement::Data(bytes) => bytes.len() as u16,
+        });
+        statements.push((i + 1, statement));
+    }
+
+    // The second pass encodes the operands.
+    let mut bytes = Vec::new();
+    for (line, statement) in statements {
+        let error = |message: String| AsmError { line, message };
+        let addr = origin.wrapping_add(bytes.len() as u16);
+        match statement {
+            Statement::Instruction { opcode, operand, length } => {
+                bytes.extend(&opcode);
+                if let Some((kind, expr)) = operand {
+                    let value = evaluate(&expr, &labels).map_err(error)?;
+                    let next = addr.wrapping_add(length) as i32;
+                    bytes.extend(encode(kind, value, next, &opcode).map_err(error)?);
+                }
+            }
+            Statement::Data(exprs) => {
+                for expr in exprs {
+                    let value = evaluate(&expr, &labels).map_err(error)?;
+                    bytes.extend(encode(Immediate::U8, value, 0, &[]).map_err(error)?);
+                }
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Assembles `source` and writes it to `memory` at `origin`.
+pub fn load(memory: &mut Memory, origin: u16, source: &str) -> Result<(), AsmError> {
+    for (i, byte) in assemble(origin, source)?.into_iter().enumerate() {
+        memory.write_byte(origin.wrapping_add(i as u16), byte);
+    }
+    Ok(())
+}
+
+/// Parses an instruction or a `db` directive.
+fn parse(code: &str) -> Result<Statement, String> {
+    let code = code.to_lowercase();
+    let (mnemonic, operands) = code.split_once(char::is_whitespace).unwrap_or((&code, ""));
+    let mut operands: Vec<String> = operands.split(',')
+        .map(|operand| operand.replace(char::is_whitespace, ""))
+        .filter(|operand| !operand.is_empty())
+        .collect();
+
+    if mnemonic == "db" || mnemonic == ".db" {
+        return Ok(Statement::Data(operands));
+    }
+
+    let mut mnemonic = mnemonic.to_string();
+    for operand in &mut operands {
+        *operand = normalise_operand(operand, &mnemonic);
+    }
+    if mnemonic == "ldh" {
+        mnemonic = "ld".to_string();
+    }
+    if ALU.contains(&mnemonic.as_str()) && operands.len() == 1 {
+        operands.insert(0, "a".to_string());
+    }
+
+    let prefixed = METADATA_PREFIX.iter().enumerate().map(|(opcode, metadata)| (vec![0xcb, opcode as u8], metadata));
+    let unprefixed = METADATA.iter().enumerate().map(|(opcode, metadata)| (vec![opcode as u8], metadata));
+    for (opcode, metadata) in unprefixed.chain(prefixed) {
+        let template = metadata.mnemonic.to_lowercase();
+        if let Some(operand) = matches(&template, &mnemonic, &operands) {
+            let length = metadata.length as u16;
+            if opcode == [0x10] {
+                // STOP is followed by a padding byte.
+                let opcode = vec![0x10, 0x00];
+                return Ok(Statement::Instruction { opcode, operand: None, length });
+            }
+            return Ok(Statement::Instruction { opcode, operand, length });
+        }
+    }
+    Err(format!("unknown instruction `{}`", code))
+}
+
+/// Rewrites the RGBDS spellings of `operand` into those of the instruction templates.
+/// Only whole operands are rewritten, so that labels such as `child` are left alone.
+fn normalise_operand(operand: &str, mnemonic: &str) -> String {
+    let mut operand = match operand.replace('[', "(").replace(']', ")").as_str() {
+        "(hli)" => "(hl+)".to_string(),
+        "(hld)" => "(hl-)".to_string(),
+        operand => operand.to_string(),
+    };
+    if let Some(offset) = operand.strip_prefix("sp-") {
+        operand = format!("sp+-{}", offset);
+    }
+    if let Some(offset) = operand.strip_prefix("($ff00+") {
+        operand = format!("(ff00+{}", offset);
+    }
+
+    if mnemonic == "ldh" && operand.starts_with('(') && !operand.starts_with("(ff00+") {
+        operand = format!("(ff00+{}", &operand[1..]);
+    }
+    if mnemonic == "rst" {
+        if let Ok(vector) = evaluate(&operand, &HashMap::new()) {
+            operand = format!("{:02x}h", vector);
+        }
+    }
+    operand
+}
+
+/// Returns whether `operands` match the instruction `template`, such as `ld (ff00+u8), a`,
+/// and if so its immediate operand's kind and expression.
+fn matches(template: &str, mnemonic: &str, operands: &[String]) -> Option<Option<(Immediate, String)>> {
+    let (name, template_operands) = template.split_once(' ').unwrap_or((template, ""));
+    let template_operands: Vec<&str> = template_operands.split(", ")
+        .filter(|operand| !operand.is_empty())
+        .collect();
+    if name != mnemonic || template_operands.len() != operands.len() {
+        return None;
+    }
+
+    let mut immediate = None;
+    for (template, operand) in template_operands.iter().zip(operands) {
+        let placeholder = [("u16", Immediate::U16), ("u8", Immediate::U8), ("i8", Immediate::I8)]
+            .iter()
+            .find(|(placeholder, _)| template.contains(placeholder))
+            .copied();
+        match placeholder {
+            None if template == operand => {}
+            None => return None,
+            Some((placeholder, kind)) => {
+                let (prefix, suffix) = template.split_once(placeholder).unwrap();
+                let expr = operand.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                if !is_expression(expr) {
+                    return None;
+                }
+                immediate = Some((kind, expr.to_string()));
+            }
+        }
+    }
+    Some(immediate)
+}
+
+/// Returns whether `expr` is a number or a label.
+fn is_expression(expr: &str) -> bool {
+    let expr = expr.trim_start_matches(['+', '-']);
+    expr.starts_with(['$', '%']) || expr.starts_with(|c: char| c.is_ascii_digit()) || is_identifier(expr)
+}
+
+/// Returns whether `name` can be a label.
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && !RESERVED.contains(&name)
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Returns the value of the number or label `expr`.
+fn evaluate(expr: &str, labels: &HashMap<String, u16>) -> Result<i32, String> {
+    let (sign, digits) = match expr.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, expr.trim_start_matches('+')),
+    };
+    let value = if let Some(hex) = digits.strip_prefix('$') {
+        i32::from_str_radix(hex, 16)
+    } else if let Some(binary) = digits.strip_prefix('%') {
+        i32::from_str_radix(binary, 2)
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse()
+    } else {
+        return labels.get(digits)
+            .map(|&addr| sign * addr as i32)
+            .ok_or_else(|| format!("unknown label `{}`", digits));
+    };
+    value.map(|value| sign * value).map_err(|_| format!("invalid number `{}`", expr))
+}
+
+/// Encodes the immediate `value` of the instruction `opcode`, whose next instruction is at `next`.
+fn encode(kind: Immediate, value: i32, next: i32, opcode: &[u8]) -> Result<Vec<u8>, String> {
+    let out_of_range = || format!("{} is out of range", value);
+    match kind {
+        Immediate::U8 if (-0x80..=0xff).contains(&value) => Ok(vec![value as u8]),
+        Immediate::U16 if (-0x8000..=0xffff).contains(&value) => Ok((value as u16).to_le_bytes().to_vec()),
+        Immediate::I8 => {
+            // The operand of JR is the target, the others take the offset itself.
+            let is_jr = opcode[0] == 0x18 || opcode[0] & 0xe7 == 0x20;
+            let offset = if is_jr { value - next } else { value };
+            if (-0x80..=0x7f).contains(&offset) {
+                Ok(vec![offset as u8])
+            } else {
+                Err(out_of_range())
+            }
+        }
+        _ => Err(out_of_range()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm;
+
+    #[test]
+    fn assembles_rgbds_syntax() {
+        let bytes = assemble(0x0100, "
+            ld sp, $fffe        ; comments are ignored
+            ld [hl+], a
+            ld a, [hld]
+            ldh [$47], a
+            ldh a, [c]
+            ld [$ff00+c], a
+            cp $34
+            sub a, b
+            bit 7, h
+            ld hl, sp-2
+            rst $38
+            stop
+            db $12, %101, 7
+        ").unwrap();
+
+        assert_eq!(bytes, [
+            0x31, 0xfe, 0xff,
+            0x22,
+            0x3a,
+            0xe0, 0x47,
+            0xf2,
+            0xe2,
+            0xfe, 0x34,
+            0x90,
+            0xcb, 0x7c,
+            0xf8, 0xfe,
+            0xff,
+            0x10, 0x00,
+            0x12, 0x05, 0x07,
+        ]);
+    }
+
+    #[test]
+    fn resolves_labels_forwards_and_backwards() {
+        let bytes = assemble(0x0200, "
+        start:
+            jr z, end
+            call start
+            jp end
+        end: nop
+        ").unwrap();
+
+        assert_eq!(bytes, [0x28, 0x06, 0xcd, 0x00, 0x02, 0xc3, 0x08, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn leaves_labels_resembling_operands_alone() {
+        let bytes = assemble(0x0300, "
+        child:
+            ld a, [hli]
+            jr nz, .shlift
+        .shlift:
+            jp child
+            call hld_sp
+        hld_sp:
+            ld hl, sp-2
+        ").unwrap();
+
+        assert_eq!(bytes, [
+            0x2a,
+            0x20, 0x00,
+            0xc3, 0x00, 0x03,
+            0xcd, 0x09, 0x03,
+            0xf8, 0xfe,
+        ]);
+    }
+
+    #[test]
+    fn reports_errors_with_their_line() {
+        let error = |source| assemble(0, source).unwrap_err();
+
+        assert_eq!(error("nop\nld q, 1").line, 2);
+        assert!(error("jp nowhere").message.contains("unknown label"));
+        assert!(error("ld a, $100").message.contains("out of range"));
+        assert!(error("x: nop\nx: nop").message.contains("duplicate label"));
+        let far = format!("jr far\n{}far: nop", "nop\n".repeat(0x80));
+        assert!(error(&far).message.contains("out of range"));
+    }
+
+    #[test]
+    fn assembles_the_disassembly_of_every_instruction() {
+        for opcode in 0..=0xff {
+            // The byte following STOP is always assembled as zero.
+            let operand = if opcode == 0x10 { 0x00 } else { 0x12 };
+            for bytes in [vec![opcode, operand, 0x34], vec![0xcb, opcode]] {
+                let decoded = disasm::decode(&bytes, 0x0100).unwrap();
+                let assembled = assemble(0x0100, &decoded.text)
+                    .unwrap_or_else(|err| panic!("{}: {}", decoded.text, err));
+                assert_eq!(assembled, decoded.bytes, "{}", decoded.text);
+            }
+        }
+    }
+}
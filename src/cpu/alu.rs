@@ -0,0 +1,403 @@
+//! The arithmetic and logic unit.
+//!
+//! Every function is pure: it takes the operands (and the current flags where
+//! the operation depends on or preserves some of them) and returns a tuple
+//! of the result and the complete new value of the F register.
+
+use crate::cpu::registers::{ Flags, Regs };
+
+/// Returns `Flags::Z` if `value` is zero.
+fn zero_flag(value: u8) -> u8 {
+    if value == 0 { Flags::Z } else { 0 }
+}
+
+/// Returns `Flags::C` if `carry` is set.
+fn carry_flag(carry: bool) -> u8 {
+    if carry { Flags::C } else { 0 }
+}
+
+/// Returns the C flag in `flags` as a 0 or 1.
+fn carry_in(flags: u8) -> u8 {
+    Regs::check_bits_8bit(flags, Flags::C) as u8
+}
+
+/// ADD `a`, `b`.
+pub fn add(a: u8, b: u8) -> (u8, u8) {
+    adc(a, b, 0)
+}
+
+/// ADC `a`, `b`.
+/// Adds `b` and the C flag in `flags` to `a`.
+pub fn adc(a: u8, b: u8, flags: u8) -> (u8, u8) {
+    let carry = carry_in(flags);
+    let sum = a as u16 + b as u16 + carry as u16;
+    let result = sum as u8;
+
+    let mut new_flags = zero_flag(result) | carry_flag(sum > 0xff);
+    // Only a carry out of the lower nibble sets the H flag.
+    if (a & 0x0f) + (b & 0x0f) + carry > 0x0f {
+        new_flags |= Flags::H;
+    }
+
+    (result, new_flags)
+}
+
+/// SUB `a`, `b`.
+pub fn sub(a: u8, b: u8) -> (u8, u8) {
+    sbc(a, b, 0)
+}
+
+/// SBC `a`, `b`.
+/// Subtracts `b` and the C flag in `flags` from `a`.
+pub fn sbc(a: u8, b: u8, flags: u8) -> (u8, u8) {
+    let carry = carry_in(flags);
+    let result = a.wrapping_sub(b).wrapping_sub(carry);
+
+    let mut new_flags = Flags::N
+        | zero_flag(result)
+        | carry_flag((a as u16) < b as u16 + carry as u16);
+    // Only a borrow from bit 4 sets the H flag.
+    if a & 0x0f < (b & 0x0f) + carry {
+        new_flags |= Flags::H;
+    }
+
+    (result, new_flags)
+}
+
+/// CP `a`, `b`.
+/// Returns only the flags, as CP discards the result of the subtraction.
+pub fn cp(a: u8, b: u8) -> u8 {
+    sub(a, b).1
+}
+
+/// AND `a`, `b`.
+/// Always sets the H flag.
+pub fn and(a: u8, b: u8) -> (u8, u8) {
+    let result = a & b;
+    (result, zero_flag(result) | Flags::H)
+}
+
+/// XOR `a`, `b`.
+pub fn xor(a: u8, b: u8) -> (u8, u8) {
+    let result = a ^ b;
+    (result, zero_flag(result))
+}
+
+/// OR `a`, `b`.
+pub fn or(a: u8, b: u8) -> (u8, u8) {
+    let result = a | b;
+    (result, zero_flag(result))
+}
+
+/// INC `value`.
+/// The C flag is kept from `flags`.
+pub fn inc(value: u8, flags: u8) -> (u8, u8) {
+    let result = value.wrapping_add(1);
+
+    let mut new_flags = zero_flag(result) | (flags & Flags::C);
+    // Only if the value's lowest 4 bits are set will it overflow
+    // from bit 3 when adding 1.
+    if value & 0x0f == 0x0f {
+        new_flags |= Flags::H;
+    }
+
+    (result, new_flags)
+}
+
+/// DEC `value`.
+/// The C flag is kept from `flags`.
+pub fn dec(value: u8, flags: u8) -> (u8, u8) {
+    let result = value.wrapping_sub(1);
+
+    let mut new_flags = Flags::N | zero_flag(result) | (flags & Flags::C);
+    // Only if the value's lowest 4 bits are all unset will it have to
+    // borrow from bit 4 when subtracting 1.
+    if value & 0x0f == 0 {
+        new_flags |= Flags::H;
+    }
+
+    (result, new_flags)
+}
+
+/// RLC `value`.
+/// Rotates `value` one bit to the left. Bit 7 goes into both bit 0 and the C flag.
+pub fn rlc(value: u8) -> (u8, u8) {
+    let result = value.rotate_left(1);
+    (result, zero_flag(result) | carry_flag(value & 0x80 != 0))
+}
+
+/// RRC `value`.
+/// Rotates `value` one bit to the right. Bit 0 goes into both bit 7 and the C flag.
+pub fn rrc(value: u8) -> (u8, u8) {
+    let result = value.rotate_right(1);
+    (result, zero_flag(result) | carry_flag(value & 1 != 0))
+}
+
+/// RL `value`.
+/// Rotates `value` one bit to the left through the C flag in `flags`.
+pub fn rl(value: u8, flags: u8) -> (u8, u8) {
+    let result = (value << 1) | carry_in(flags);
+    (result, zero_flag(result) | carry_flag(value & 0x80 != 0))
+}
+
+/// RR `value`.
+/// Rotates `value` one bit to the right through the C flag in `flags`.
+pub fn rr(value: u8, flags: u8) -> (u8, u8) {
+    let result = (value >> 1) | (carry_in(flags) << 7);
+    (result, zero_flag(result) | carry_flag(value & 1 != 0))
+}
+
+/// SLA `value`.
+/// Shifts `value` one bit to the left. Bit 7 goes into the C flag.
+pub fn sla(value: u8) -> (u8, u8) {
+    let result = value << 1;
+    (result, zero_flag(result) | carry_flag(value & 0x80 != 0))
+}
+
+/// SRA `value`.
+/// Shifts `value` one bit to the right, keeping bit 7. Bit 0 goes into the C flag.
+pub fn sra(value: u8) -> (u8, u8) {
+    let result = (value >> 1) | (value & 0x80);
+    (result, zero_flag(result) | carry_flag(value & 1 != 0))
+}
+
+/// SWAP `value`.
+/// Swaps the upper and lower nibbles of `value`.
+pub fn swap(value: u8) -> (u8, u8) {
+    let result = value.rotate_left(4);
+    (result, zero_flag(result))
+}
+
+/// SRL `value`.
+/// Shifts `value` one bit to the right. Bit 0 goes into the C flag.
+pub fn srl(value: u8) -> (u8, u8) {
+    let result = value >> 1;
+    (result, zero_flag(result) | carry_flag(value & 1 != 0))
+}
+
+/// DAA
+/// Adjusts `a` to hold the binary coded decimal result of the last addition
+/// or subtraction, as told by the N, H and C flags in `flags`.
+pub fn daa(a: u8, flags: u8) -> (u8, u8) {
+    let subtract = Regs::check_bits_8bit(flags, Flags::N);
+    let half_carry = Regs::check_bits_8bit(flags, Flags::H);
+    let mut carry = Regs::check_bits_8bit(flags, Flags::C);
+
+    let mut correction = 0;
+    if half_carry || (!subtract && a & 0x0f > 0x09) {
+        correction |= 0x06;
+    }
+    if carry || (!subtract && a > 0x99) {
+        correction |= 0x60;
+        carry = true;
+    }
+
+    let result = if subtract {
+        a.wrapping_sub(correction)
+    } else {
+        a.wrapping_add(correction)
+    };
+
+    (result, (flags & Flags::N) | zero_flag(result) | carry_flag(carry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_FLAGS: [u8; 2] = [0, Flags::C];
+
+    /// Returns the expected flags from the full width `result` of an 8 bit
+    /// addition or subtraction of `a` and `b`. The H flag is found by checking
+    /// whether bit 4 of the result differs from the sum of the bits 4 of the operands.
+    fn expected_flags(a: u8, b: u8, result: i16, subtract: bool) -> u8 {
+        let mut flags = 0;
+        if result as u8 == 0 {
+            flags |= Flags::Z;
+        }
+        if subtract {
+            flags |= Flags::N;
+        }
+        if (a as i16 ^ b as i16 ^ result) & 0x10 != 0 {
+            flags |= Flags::H;
+        }
+        if !(0..=0xff).contains(&result) {
+            flags |= Flags::C;
+        }
+        flags
+    }
+
+    #[test]
+    fn add_and_adc_match_wide_addition() {
+        for a in 0..=0xff {
+            for b in 0..=0xff {
+                for &flags in &ALL_FLAGS {
+                    let carry = (flags != 0) as i16;
+                    let result = a as i16 + b as i16 + carry;
+                    let expected = (result as u8, expected_flags(a, b, result, false));
+
+                    assert_eq!(adc(a, b, flags), expected, "ADC {:#04x}, {:#04x}, F={:#04x}", a, b, flags);
+                    if flags == 0 {
+                        assert_eq!(add(a, b), expected, "ADD {:#04x}, {:#04x}", a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sub_sbc_and_cp_match_wide_subtraction() {
+        for a in 0..=0xff {
+            for b in 0..=0xff {
+                for &flags in &ALL_FLAGS {
+                    let carry = (flags != 0) as i16;
+                    let result = a as i16 - b as i16 - carry;
+                    let expected = (result as u8, expected_flags(a, b, result, true));
+
+                    assert_eq!(sbc(a, b, flags), expected, "SBC {:#04x}, {:#04x}, F={:#04x}", a, b, flags);
+                    if flags == 0 {
+                        assert_eq!(sub(a, b), expected, "SUB {:#04x}, {:#04x}", a, b);
+                        assert_eq!(cp(a, b), expected.1, "CP {:#04x}, {:#04x}", a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn logic_operations() {
+        for a in 0..=0xff {
+            for b in 0..=0xff {
+                let z = |result: u8| if result == 0 { Flags::Z } else { 0 };
+                assert_eq!(and(a, b), (a & b, z(a & b) | Flags::H));
+                assert_eq!(xor(a, b), (a ^ b, z(a ^ b)));
+                assert_eq!(or(a, b), (a | b, z(a | b)));
+            }
+        }
+    }
+
+    #[test]
+    fn inc_and_dec_keep_the_carry_flag() {
+        for value in 0..=0xff {
+            for &flags in &ALL_FLAGS {
+                // INC and DEC behave like ADD and SUB with 1, except for the C flag.
+                let (sum, sum_flags) = add(value, 1);
+                assert_eq!(inc(value, flags), (sum, (sum_flags & !Flags::C) | flags));
+
+                let (diff, diff_flags) = sub(value, 1);
+                assert_eq!(dec(value, flags), (diff, (diff_flags & !Flags::C) | flags));
+            }
+        }
+    }
+
+    #[test]
+    fn rotates_and_shifts() {
+        for value in 0..=0xff {
+            let wide = value as u16;
+            let z = |result: u8| if result == 0 { Flags::Z } else { 0 };
+            let c = |bit: u16| if bit != 0 { Flags::C } else { 0 };
+
+            let result = ((wide << 1) | (wide >> 7)) as u8;
+            assert_eq!(rlc(value), (result, z(result) | c(wide & 0x80)));
+
+            let result = ((wide >> 1) | (wide << 7)) as u8;
+            assert_eq!(rrc(value), (result, z(result) | c(wide & 1)));
+
+            for &flags in &ALL_FLAGS {
+                let carry = (flags != 0) as u16;
+
+                let result = ((wide << 1) | carry) as u8;
+                assert_eq!(rl(value, flags), (result, z(result) | c(wide & 0x80)));
+
+                let result = ((wide >> 1) | (carry << 7)) as u8;
+                assert_eq!(rr(value, flags), (result, z(result) | c(wide & 1)));
+
+                // Rotating through the carry and back restores the value and the carry.
+                let (rotated, rotated_flags) = rl(value, flags);
+                assert_eq!(rr(rotated, rotated_flags), (value, z(value) | flags));
+            }
+
+            let result = (wide << 1) as u8;
+            assert_eq!(sla(value), (result, z(result) | c(wide & 0x80)));
+
+            let result = ((value as i8) >> 1) as u8;
+            assert_eq!(sra(value), (result, z(result) | c(wide & 1)));
+
+            let result = (wide >> 1) as u8;
+            assert_eq!(srl(value), (result, z(result) | c(wide & 1)));
+
+            let result = ((wide & 0x0f) << 4 | wide >> 4) as u8;
+            assert_eq!(swap(value), (result, z(result)));
+        }
+    }
+
+    /// Returns the expected result of DAA, computed on a signed accumulator
+    /// the way the hardware's adjustment is usually described: the carry out
+    /// of bit 8 of the adjusted value ends up in the C flag.
+    fn reference_daa(a: u8, flags: u8) -> (u8, u8) {
+        let subtract = flags & Flags::N != 0;
+        let half_carry = flags & Flags::H != 0;
+        let carry = flags & Flags::C != 0;
+
+        let mut result = a as i16;
+        if subtract {
+            if half_carry {
+                result = (result - 0x06) & 0xff;
+            }
+            if carry {
+                result -= 0x60;
+            }
+        } else {
+            if half_carry || result & 0x0f > 0x09 {
+                result += 0x06;
+            }
+            if carry || result > 0x9f {
+                result += 0x60;
+            }
+        }
+
+        let mut new_flags = flags & (Flags::N | Flags::C);
+        if result & 0xff == 0 {
+            new_flags |= Flags::Z;
+        }
+        if result & 0x100 != 0 {
+            new_flags |= Flags::C;
+        }
+        (result as u8, new_flags)
+    }
+
+    #[test]
+    fn daa_matches_the_reference_for_every_input() {
+        // Every value of A with every combination of N, H and C: 256 * 8 = 2048 cases.
+        let table: Vec<(u8, u8, (u8, u8))> = (0..=0xff)
+            .flat_map(|a| (0..8).map(move |nhc: u8| (a, nhc << 4)))
+            .map(|(a, flags)| (a, flags, reference_daa(a, flags)))
+            .collect();
+        assert_eq!(table.len(), 2048);
+
+        for (a, flags, expected) in table {
+            assert_eq!(daa(a, flags), expected, "DAA A={:#04x}, F={:#010b}", a, flags);
+            // The Z flag going in makes no difference.
+            assert_eq!(daa(a, flags | Flags::Z), expected, "DAA A={:#04x}, F={:#010b}", a, flags | Flags::Z);
+        }
+    }
+
+    #[test]
+    fn daa_corrects_bcd_addition_and_subtraction() {
+        let bcd = |x: u8| ((x / 10) << 4) | (x % 10);
+
+        for x in 0..100 {
+            for y in 0..100 {
+                let (sum, flags) = add(bcd(x), bcd(y));
+                let (result, flags) = daa(sum, flags);
+                assert_eq!(result, bcd((x + y) % 100), "{} + {}", x, y);
+                assert_eq!(flags & Flags::C != 0, x + y >= 100, "{} + {}", x, y);
+
+                let (diff, flags) = sub(bcd(x), bcd(y));
+                let (result, flags) = daa(diff, flags);
+                assert_eq!(result, bcd((x + 100 - y) % 100), "{} - {}", x, y);
+                assert_eq!(flags & Flags::C != 0, x < y, "{} - {}", x, y);
+            }
+        }
+    }
+}
@@ -1,74 +1,525 @@
-use std::collections::HashMap;
-
-use lazy_static::lazy_static;
-
 use crate::cpu::Cpu;
 use crate::memory::Memory;
 
-pub enum InstructionAccess {
-    Cpu(fn(&mut Cpu)),
-    CpuWithMemory(fn(&mut Cpu, &mut Memory)),
-}
-
-lazy_static!(
-    pub static ref INSTRS: HashMap<u8, InstructionAccess> = HashMap::from([
-        (0x00, InstructionAccess::Cpu(super::op_00)), // NOP
-        (0x04, InstructionAccess::Cpu(super::op_04)), // INC B
-        (0x05, InstructionAccess::Cpu(super::op_05)), // DEC B
-        (0x06, InstructionAccess::CpuWithMemory(super::op_06)), // LD B, u8
-        (0x0c, InstructionAccess::Cpu(super::op_0c)), // INC C
-        (0x0d, InstructionAccess::Cpu(super::op_0d)), // INC C
-        (0x0e, InstructionAccess::CpuWithMemory(super::op_0e)), // LD C, u8
-        (0x11, InstructionAccess::CpuWithMemory(super::op_11)), // LD DE, u16
-        (0x13, InstructionAccess::Cpu(super::op_13)), // INC DE
-        (0x15, InstructionAccess::Cpu(super::op_15)), // DEC D
-        (0x16, InstructionAccess::CpuWithMemory(super::op_16)), // LD D, u8
-        (0x17, InstructionAccess::Cpu(super::op_17)), // RLA
-        (0x18, InstructionAccess::CpuWithMemory(super::op_18)), // JR i8
-        (0x1a, InstructionAccess::CpuWithMemory(super::op_1a)), // LD A, (DE)
-        (0x1d, InstructionAccess::Cpu(super::op_1d)), // DEC E
-        (0x1e, InstructionAccess::CpuWithMemory(super::op_1e)), // LD E, u8
-        (0x20, InstructionAccess::CpuWithMemory(super::op_20)), // JR NZ, i8
-        (0x21, InstructionAccess::CpuWithMemory(super::op_21)), // LD HL, u16
-        (0x22, InstructionAccess::CpuWithMemory(super::op_22)), // LD (HL+), A
-        (0x23, InstructionAccess::Cpu(super::op_23)), // INC HL
-        (0x24, InstructionAccess::Cpu(super::op_24)), // INC H
-        (0x28, InstructionAccess::CpuWithMemory(super::op_28)), // JR Z, i8
-        (0x31, InstructionAccess::CpuWithMemory(super::op_31)), // LD SP, u16
-        (0x32, InstructionAccess::CpuWithMemory(super::op_32)), // LD (HL-), A
-        (0x3d, InstructionAccess::Cpu(super::op_3d)), // DEC A
-        (0x3e, InstructionAccess::CpuWithMemory(super::op_3e)), // LD A, u8
-        (0x4f, InstructionAccess::Cpu(super::op_4f)), // LD C, A
-        (0x57, InstructionAccess::Cpu(super::op_57)), // LD D, A
-        (0x67, InstructionAccess::Cpu(super::op_67)), // LD H, A
-        (0x77, InstructionAccess::CpuWithMemory(super::op_77)), // LD (HL), A
-        (0x7b, InstructionAccess::Cpu(super::op_7b)), // LD A, E
-        (0x7c, InstructionAccess::Cpu(super::op_7c)), // LD A, H
-        (0x80, InstructionAccess::Cpu(super::op_80)), // ADD A, B
-        (0x90, InstructionAccess::Cpu(super::op_90)), // SUB A, B
-        (0xaf, InstructionAccess::Cpu(super::op_af)), // XOR A, A
-        (0xc1, InstructionAccess::CpuWithMemory(super::op_c1)), // POP BC
-        (0xc5, InstructionAccess::CpuWithMemory(super::op_c5)), // PUSH BC
-        (0xc9, InstructionAccess::CpuWithMemory(super::op_c9)), // RET
-        (0xcb, InstructionAccess::CpuWithMemory(super::op_cb)), // Prefixed instructions...
-        (0xcd, InstructionAccess::CpuWithMemory(super::op_cd)), // CALL u16
-        (0xe0, InstructionAccess::CpuWithMemory(super::op_e0)), // LD (FF00+u8), A
-        (0xe2, InstructionAccess::CpuWithMemory(super::op_e2)), // LD (FF00+C), A
-        (0xea, InstructionAccess::CpuWithMemory(super::op_ea)), // LD (u16), A
-        (0xf0, InstructionAccess::CpuWithMemory(super::op_f0)), // LD A, (FF00+u8)
-        (0xf3, InstructionAccess::Cpu(super::op_f3)), // DI
-        (0xfe, InstructionAccess::CpuWithMemory(super::op_fe)), // CP A, u8 (or just: CP u8)
-    ]);
+/// The signature shared by every instruction, returning the T-cycles it took.
+pub type Instruction = fn(&mut Cpu, &mut Memory) -> u8;
 
-    pub static ref INSTRS_PREFIX: HashMap<u8, InstructionAccess> = HashMap::from([
-        (0x11, InstructionAccess::Cpu(super::op_cb11)), // RL C
-        (0x17, InstructionAccess::Cpu(super::op_cb17)), // RL A
-        (0x7c, InstructionAccess::Cpu(super::op_cb7c)), // BIT 7, H
-    ]);
-);
+/// The unprefixed instructions, indexed by their opcode.
+pub static INSTRS: [Instruction; 0x100] = [
+    super::op_00, // 0x00: NOP
+    super::ld_rp_u16, // 0x01: LD BC, u16
+    super::op_02, // 0x02: LD (BC), A
+    super::inc_rp, // 0x03: INC BC
+    super::inc_r, // 0x04: INC B
+    super::dec_r, // 0x05: DEC B
+    super::ld_r_u8, // 0x06: LD B, u8
+    super::op_07, // 0x07: RLCA
+    super::op_08, // 0x08: LD (u16), SP
+    super::add_hl_rp, // 0x09: ADD HL, BC
+    super::op_0a, // 0x0a: LD A, (BC)
+    super::dec_rp, // 0x0b: DEC BC
+    super::inc_r, // 0x0c: INC C
+    super::dec_r, // 0x0d: DEC C
+    super::ld_r_u8, // 0x0e: LD C, u8
+    super::op_0f, // 0x0f: RRCA
+    super::op_10, // 0x10: STOP
+    super::ld_rp_u16, // 0x11: LD DE, u16
+    super::op_12, // 0x12: LD (DE), A
+    super::inc_rp, // 0x13: INC DE
+    super::inc_r, // 0x14: INC D
+    super::dec_r, // 0x15: DEC D
+    super::ld_r_u8, // 0x16: LD D, u8
+    super::op_17, // 0x17: RLA
+    super::op_18, // 0x18: JR i8
+    super::add_hl_rp, // 0x19: ADD HL, DE
+    super::op_1a, // 0x1a: LD A, (DE)
+    super::dec_rp, // 0x1b: DEC DE
+    super::inc_r, // 0x1c: INC E
+    super::dec_r, // 0x1d: DEC E
+    super::ld_r_u8, // 0x1e: LD E, u8
+    super::op_1f, // 0x1f: RRA
+    super::jr_cc, // 0x20: JR NZ, i8
+    super::ld_rp_u16, // 0x21: LD HL, u16
+    super::op_22, // 0x22: LD (HL+), A
+    super::inc_rp, // 0x23: INC HL
+    super::inc_r, // 0x24: INC H
+    super::dec_r, // 0x25: DEC H
+    super::ld_r_u8, // 0x26: LD H, u8
+    super::op_27, // 0x27: DAA
+    super::jr_cc, // 0x28: JR Z, i8
+    super::add_hl_rp, // 0x29: ADD HL, HL
+    super::op_2a, // 0x2a: LD A, (HL+)
+    super::dec_rp, // 0x2b: DEC HL
+    super::inc_r, // 0x2c: INC L
+    super::dec_r, // 0x2d: DEC L
+    super::ld_r_u8, // 0x2e: LD L, u8
+    super::op_2f, // 0x2f: CPL
+    super::jr_cc, // 0x30: JR NC, i8
+    super::ld_rp_u16, // 0x31: LD SP, u16
+    super::op_32, // 0x32: LD (HL-), A
+    super::inc_rp, // 0x33: INC SP
+    super::inc_r, // 0x34: INC (HL)
+    super::dec_r, // 0x35: DEC (HL)
+    super::ld_r_u8, // 0x36: LD (HL), u8
+    super::op_37, // 0x37: SCF
+    super::jr_cc, // 0x38: JR C, i8
+    super::add_hl_rp, // 0x39: ADD HL, SP
+    super::op_3a, // 0x3a: LD A, (HL-)
+    super::dec_rp, // 0x3b: DEC SP
+    super::inc_r, // 0x3c: INC A
+    super::dec_r, // 0x3d: DEC A
+    super::ld_r_u8, // 0x3e: LD A, u8
+    super::op_3f, // 0x3f: CCF
+    super::ld_r_r, // 0x40: LD B, B
+    super::ld_r_r, // 0x41: LD B, C
+    super::ld_r_r, // 0x42: LD B, D
+    super::ld_r_r, // 0x43: LD B, E
+    super::ld_r_r, // 0x44: LD B, H
+    super::ld_r_r, // 0x45: LD B, L
+    super::ld_r_r, // 0x46: LD B, (HL)
+    super::ld_r_r, // 0x47: LD B, A
+    super::ld_r_r, // 0x48: LD C, B
+    super::ld_r_r, // 0x49: LD C, C
+    super::ld_r_r, // 0x4a: LD C, D
+    super::ld_r_r, // 0x4b: LD C, E
+    super::ld_r_r, // 0x4c: LD C, H
+    super::ld_r_r, // 0x4d: LD C, L
+    super::ld_r_r, // 0x4e: LD C, (HL)
+    super::ld_r_r, // 0x4f: LD C, A
+    super::ld_r_r, // 0x50: LD D, B
+    super::ld_r_r, // 0x51: LD D, C
+    super::ld_r_r, // 0x52: LD D, D
+    super::ld_r_r, // 0x53: LD D, E
+    super::ld_r_r, // 0x54: LD D, H
+    super::ld_r_r, // 0x55: LD D, L
+    super::ld_r_r, // 0x56: LD D, (HL)
+    super::ld_r_r, // 0x57: LD D, A
+    super::ld_r_r, // 0x58: LD E, B
+    super::ld_r_r, // 0x59: LD E, C
+    super::ld_r_r, // 0x5a: LD E, D
+    super::ld_r_r, // 0x5b: LD E, E
+    super::ld_r_r, // 0x5c: LD E, H
+    super::ld_r_r, // 0x5d: LD E, L
+    super::ld_r_r, // 0x5e: LD E, (HL)
+    super::ld_r_r, // 0x5f: LD E, A
+    super::ld_r_r, // 0x60: LD H, B
+    super::ld_r_r, // 0x61: LD H, C
+    super::ld_r_r, // 0x62: LD H, D
+    super::ld_r_r, // 0x63: LD H, E
+    super::ld_r_r, // 0x64: LD H, H
+    super::ld_r_r, // 0x65: LD H, L
+    super::ld_r_r, // 0x66: LD H, (HL)
+    super::ld_r_r, // 0x67: LD H, A
+    super::ld_r_r, // 0x68: LD L, B
+    super::ld_r_r, // 0x69: LD L, C
+    super::ld_r_r, // 0x6a: LD L, D
+    super::ld_r_r, // 0x6b: LD L, E
+    super::ld_r_r, // 0x6c: LD L, H
+    super::ld_r_r, // 0x6d: LD L, L
+    super::ld_r_r, // 0x6e: LD L, (HL)
+    super::ld_r_r, // 0x6f: LD L, A
+    super::ld_r_r, // 0x70: LD (HL), B
+    super::ld_r_r, // 0x71: LD (HL), C
+    super::ld_r_r, // 0x72: LD (HL), D
+    super::ld_r_r, // 0x73: LD (HL), E
+    super::ld_r_r, // 0x74: LD (HL), H
+    super::ld_r_r, // 0x75: LD (HL), L
+    super::op_76, // 0x76: HALT
+    super::ld_r_r, // 0x77: LD (HL), A
+    super::ld_r_r, // 0x78: LD A, B
+    super::ld_r_r, // 0x79: LD A, C
+    super::ld_r_r, // 0x7a: LD A, D
+    super::ld_r_r, // 0x7b: LD A, E
+    super::ld_r_r, // 0x7c: LD A, H
+    super::ld_r_r, // 0x7d: LD A, L
+    super::ld_r_r, // 0x7e: LD A, (HL)
+    super::ld_r_r, // 0x7f: LD A, A
+    super::alu_a_r, // 0x80: ADD A, B
+    super::alu_a_r, // 0x81: ADD A, C
+    super::alu_a_r, // 0x82: ADD A, D
+    super::alu_a_r, // 0x83: ADD A, E
+    super::alu_a_r, // 0x84: ADD A, H
+    super::alu_a_r, // 0x85: ADD A, L
+    super::alu_a_r, // 0x86: ADD A, (HL)
+    super::alu_a_r, // 0x87: ADD A, A
+    super::alu_a_r, // 0x88: ADC A, B
+    super::alu_a_r, // 0x89: ADC A, C
+    super::alu_a_r, // 0x8a: ADC A, D
+    super::alu_a_r, // 0x8b: ADC A, E
+    super::alu_a_r, // 0x8c: ADC A, H
+    super::alu_a_r, // 0x8d: ADC A, L
+    super::alu_a_r, // 0x8e: ADC A, (HL)
+    super::alu_a_r, // 0x8f: ADC A, A
+    super::alu_a_r, // 0x90: SUB A, B
+    super::alu_a_r, // 0x91: SUB A, C
+    super::alu_a_r, // 0x92: SUB A, D
+    super::alu_a_r, // 0x93: SUB A, E
+    super::alu_a_r, // 0x94: SUB A, H
+    super::alu_a_r, // 0x95: SUB A, L
+    super::alu_a_r, // 0x96: SUB A, (HL)
+    super::alu_a_r, // 0x97: SUB A, A
+    super::alu_a_r, // 0x98: SBC A, B
+    super::alu_a_r, // 0x99: SBC A, C
+    super::alu_a_r, // 0x9a: SBC A, D
+    super::alu_a_r, // 0x9b: SBC A, E
+    super::alu_a_r, // 0x9c: SBC A, H
+    super::alu_a_r, // 0x9d: SBC A, L
+    super::alu_a_r, // 0x9e: SBC A, (HL)
+    super::alu_a_r, // 0x9f: SBC A, A
+    super::alu_a_r, // 0xa0: AND A, B
+    super::alu_a_r, // 0xa1: AND A, C
+    super::alu_a_r, // 0xa2: AND A, D
+    super::alu_a_r, // 0xa3: AND A, E
+    super::alu_a_r, // 0xa4: AND A, H
+    super::alu_a_r, // 0xa5: AND A, L
+    super::alu_a_r, // 0xa6: AND A, (HL)
+    super::alu_a_r, // 0xa7: AND A, A
+    super::alu_a_r, // 0xa8: XOR A, B
+    super::alu_a_r, // 0xa9: XOR A, C
+    super::alu_a_r, // 0xaa: XOR A, D
+    super::alu_a_r, // 0xab: XOR A, E
+    super::alu_a_r, // 0xac: XOR A, H
+    super::alu_a_r, // 0xad: XOR A, L
+    super::alu_a_r, // 0xae: XOR A, (HL)
+    super::alu_a_r, // 0xaf: XOR A, A
+    super::alu_a_r, // 0xb0: OR A, B
+    super::alu_a_r, // 0xb1: OR A, C
+    super::alu_a_r, // 0xb2: OR A, D
+    super::alu_a_r, // 0xb3: OR A, E
+    super::alu_a_r, // 0xb4: OR A, H
+    super::alu_a_r, // 0xb5: OR A, L
+    super::alu_a_r, // 0xb6: OR A, (HL)
+    super::alu_a_r, // 0xb7: OR A, A
+    super::alu_a_r, // 0xb8: CP A, B
+    super::alu_a_r, // 0xb9: CP A, C
+    super::alu_a_r, // 0xba: CP A, D
+    super::alu_a_r, // 0xbb: CP A, E
+    super::alu_a_r, // 0xbc: CP A, H
+    super::alu_a_r, // 0xbd: CP A, L
+    super::alu_a_r, // 0xbe: CP A, (HL)
+    super::alu_a_r, // 0xbf: CP A, A
+    super::ret_cc, // 0xc0: RET NZ
+    super::pop_rp2, // 0xc1: POP BC
+    super::jp_cc, // 0xc2: JP NZ, u16
+    super::op_c3, // 0xc3: JP u16
+    super::call_cc, // 0xc4: CALL NZ, u16
+    super::push_rp2, // 0xc5: PUSH BC
+    super::alu_a_u8, // 0xc6: ADD A, u8
+    super::rst, // 0xc7: RST 00h
+    super::ret_cc, // 0xc8: RET Z
+    super::op_c9, // 0xc9: RET
+    super::jp_cc, // 0xca: JP Z, u16
+    super::op_cb, // 0xcb: Prefixed instructions...
+    super::call_cc, // 0xcc: CALL Z, u16
+    super::op_cd, // 0xcd: CALL u16
+    super::alu_a_u8, // 0xce: ADC A, u8
+    super::rst, // 0xcf: RST 08h
+    super::ret_cc, // 0xd0: RET NC
+    super::pop_rp2, // 0xd1: POP DE
+    super::jp_cc, // 0xd2: JP NC, u16
+    super::op_illegal, // 0xd3
+    super::call_cc, // 0xd4: CALL NC, u16
+    super::push_rp2, // 0xd5: PUSH DE
+    super::alu_a_u8, // 0xd6: SUB A, u8
+    super::rst, // 0xd7: RST 10h
+    super::ret_cc, // 0xd8: RET C
+    super::op_d9, // 0xd9: RETI
+    super::jp_cc, // 0xda: JP C, u16
+    super::op_illegal, // 0xdb
+    super::call_cc, // 0xdc: CALL C, u16
+    super::op_illegal, // 0xdd
+    super::alu_a_u8, // 0xde: SBC A, u8
+    super::rst, // 0xdf: RST 18h
+    super::op_e0, // 0xe0: LD (FF00+u8), A
+    super::pop_rp2, // 0xe1: POP HL
+    super::op_e2, // 0xe2: LD (FF00+C), A
+    super::op_illegal, // 0xe3
+    super::op_illegal, // 0xe4
+    super::push_rp2, // 0xe5: PUSH HL
+    super::alu_a_u8, // 0xe6: AND A, u8
+    super::rst, // 0xe7: RST 20h
+    super::op_e8, // 0xe8: ADD SP, i8
+    super::op_e9, // 0xe9: JP HL
+    super::op_ea, // 0xea: LD (u16), A
+    super::op_illegal, // 0xeb
+    super::op_illegal, // 0xec
+    super::op_illegal, // 0xed
+    super::alu_a_u8, // 0xee: XOR A, u8
+    super::rst, // 0xef: RST 28h
+    super::op_f0, // 0xf0: LD A, (FF00+u8)
+    super::pop_rp2, // 0xf1: POP AF
+    super::op_f2, // 0xf2: LD A, (FF00+C)
+    super::op_f3, // 0xf3: DI
+    super::op_illegal, // 0xf4
+    super::push_rp2, // 0xf5: PUSH AF
+    super::alu_a_u8, // 0xf6: OR A, u8
+    super::rst, // 0xf7: RST 30h
+    super::op_f8, // 0xf8: LD HL, SP+i8
+    super::op_f9, // 0xf9: LD SP, HL
+    super::op_fa, // 0xfa: LD A, (u16)
+    super::op_fb, // 0xfb: EI
+    super::op_illegal, // 0xfc
+    super::op_illegal, // 0xfd
+    super::alu_a_u8, // 0xfe: CP A, u8
+    super::rst, // 0xff: RST 38h
+];
 
-//0xcb => { // Prefixed instructions.
-    //#[cfg(debug_assertions)]
-    //println!("prefixed instruction:");
-    //prefix(self, memory);
-//},       
+/// The instructions prefixed by 0xcb, indexed by the opcode following the prefix.
+pub static INSTRS_PREFIX: [Instruction; 0x100] = [
+    super::cb_shift, // 0x00: RLC B
+    super::cb_shift, // 0x01: RLC C
+    super::cb_shift, // 0x02: RLC D
+    super::cb_shift, // 0x03: RLC E
+    super::cb_shift, // 0x04: RLC H
+    super::cb_shift, // 0x05: RLC L
+    super::cb_shift, // 0x06: RLC (HL)
+    super::cb_shift, // 0x07: RLC A
+    super::cb_shift, // 0x08: RRC B
+    super::cb_shift, // 0x09: RRC C
+    super::cb_shift, // 0x0a: RRC D
+    super::cb_shift, // 0x0b: RRC E
+    super::cb_shift, // 0x0c: RRC H
+    super::cb_shift, // 0x0d: RRC L
+    super::cb_shift, // 0x0e: RRC (HL)
+    super::cb_shift, // 0x0f: RRC A
+    super::cb_shift, // 0x10: RL B
+    super::cb_shift, // 0x11: RL C
+    super::cb_shift, // 0x12: RL D
+    super::cb_shift, // 0x13: RL E
+    super::cb_shift, // 0x14: RL H
+    super::cb_shift, // 0x15: RL L
+    super::cb_shift, // 0x16: RL (HL)
+    super::cb_shift, // 0x17: RL A
+    super::cb_shift, // 0x18: RR B
+    super::cb_shift, // 0x19: RR C
+    super::cb_shift, // 0x1a: RR D
+    super::cb_shift, // 0x1b: RR E
+    super::cb_shift, // 0x1c: RR H
+    super::cb_shift, // 0x1d: RR L
+    super::cb_shift, // 0x1e: RR (HL)
+    super::cb_shift, // 0x1f: RR A
+    super::cb_shift, // 0x20: SLA B
+    super::cb_shift, // 0x21: SLA C
+    super::cb_shift, // 0x22: SLA D
+    super::cb_shift, // 0x23: SLA E
+    super::cb_shift, // 0x24: SLA H
+    super::cb_shift, // 0x25: SLA L
+    super::cb_shift, // 0x26: SLA (HL)
+    super::cb_shift, // 0x27: SLA A
+    super::cb_shift, // 0x28: SRA B
+    super::cb_shift, // 0x29: SRA C
+    super::cb_shift, // 0x2a: SRA D
+    super::cb_shift, // 0x2b: SRA E
+    super::cb_shift, // 0x2c: SRA H
+    super::cb_shift, // 0x2d: SRA L
+    super::cb_shift, // 0x2e: SRA (HL)
+    super::cb_shift, // 0x2f: SRA A
+    super::cb_shift, // 0x30: SWAP B
+    super::cb_shift, // 0x31: SWAP C
+    super::cb_shift, // 0x32: SWAP D
+    super::cb_shift, // 0x33: SWAP E
+    super::cb_shift, // 0x34: SWAP H
+    super::cb_shift, // 0x35: SWAP L
+    super::cb_shift, // 0x36: SWAP (HL)
+    super::cb_shift, // 0x37: SWAP A
+    super::cb_shift, // 0x38: SRL B
+    super::cb_shift, // 0x39: SRL C
+    super::cb_shift, // 0x3a: SRL D
+    super::cb_shift, // 0x3b: SRL E
+    super::cb_shift, // 0x3c: SRL H
+    super::cb_shift, // 0x3d: SRL L
+    super::cb_shift, // 0x3e: SRL (HL)
+    super::cb_shift, // 0x3f: SRL A
+    super::cb_bit, // 0x40: BIT 0, B
+    super::cb_bit, // 0x41: BIT 0, C
+    super::cb_bit, // 0x42: BIT 0, D
+    super::cb_bit, // 0x43: BIT 0, E
+    super::cb_bit, // 0x44: BIT 0, H
+    super::cb_bit, // 0x45: BIT 0, L
+    super::cb_bit, // 0x46: BIT 0, (HL)
+    super::cb_bit, // 0x47: BIT 0, A
+    super::cb_bit, // 0x48: BIT 1, B
+    super::cb_bit, // 0x49: BIT 1, C
+    super::cb_bit, // 0x4a: BIT 1, D
+    super::cb_bit, // 0x4b: BIT 1, E
+    super::cb_bit, // 0x4c: BIT 1, H
+    super::cb_bit, // 0x4d: BIT 1, L
+    super::cb_bit, // 0x4e: BIT 1, (HL)
+    super::cb_bit, // 0x4f: BIT 1, A
+    super::cb_bit, // 0x50: BIT 2, B
+    super::cb_bit, // 0x51: BIT 2, C
+    super::cb_bit, // 0x52: BIT 2, D
+    super::cb_bit, // 0x53: BIT 2, E
+    super::cb_bit, // 0x54: BIT 2, H
+    super::cb_bit, // 0x55: BIT 2, L
+    super::cb_bit, // 0x56: BIT 2, (HL)
+    super::cb_bit, // 0x57: BIT 2, A
+    super::cb_bit, // 0x58: BIT 3, B
+    super::cb_bit, // 0x59: BIT 3, C
+    super::cb_bit, // 0x5a: BIT 3, D
+    super::cb_bit, // 0x5b: BIT 3, E
+    super::cb_bit, // 0x5c: BIT 3, H
+    super::cb_bit, // 0x5d: BIT 3, L
+    super::cb_bit, // 0x5e: BIT 3, (HL)
+    super::cb_bit, // 0x5f: BIT 3, A
+    super::cb_bit, // 0x60: BIT 4, B
+    super::cb_bit, // 0x61: BIT 4, C
+    super::cb_bit, // 0x62: BIT 4, D
+    super::cb_bit, // 0x63: BIT 4, E
+    super::cb_bit, // 0x64: BIT 4, H
+    super::cb_bit, // 0x65: BIT 4, L
+    super::cb_bit, // 0x66: BIT 4, (HL)
+    super::cb_bit, // 0x67: BIT 4, A
+    super::cb_bit, // 0x68: BIT 5, B
+    super::cb_bit, // 0x69: BIT 5, C
+    super::cb_bit, // 0x6a: BIT 5, D
+    super::cb_bit, // 0x6b: BIT 5, E
+    super::cb_bit, // 0x6c: BIT 5, H
+    super::cb_bit, // 0x6d: BIT 5, L
+    super::cb_bit, // 0x6e: BIT 5, (HL)
+    super::cb_bit, // 0x6f: BIT 5, A
+    super::cb_bit, // 0x70: BIT 6, B
+    super::cb_bit, // 0x71: BIT 6, C
+    super::cb_bit, // 0x72: BIT 6, D
+    super::cb_bit, // 0x73: BIT 6, E
+    super::cb_bit, // 0x74: BIT 6, H
+    super::cb_bit, // 0x75: BIT 6, L
+    super::cb_bit, // 0x76: BIT 6, (HL)
+    super::cb_bit, // 0x77: BIT 6, A
+    super::cb_bit, // 0x78: BIT 7, B
+    super::cb_bit, // 0x79: BIT 7, C
+    super::cb_bit, // 0x7a: BIT 7, D
+    super::cb_bit, // 0x7b: BIT 7, E
+    super::cb_bit, // 0x7c: BIT 7, H
+    super::cb_bit, // 0x7d: BIT 7, L
+    super::cb_bit, // 0x7e: BIT 7, (HL)
+    super::cb_bit, // 0x7f: BIT 7, A
+    super::cb_res, // 0x80: RES 0, B
+    super::cb_res, // 0x81: RES 0, C
+    super::cb_res, // 0x82: RES 0, D
+    super::cb_res, // 0x83: RES 0, E
+    super::cb_res, // 0x84: RES 0, H
+    super::cb_res, // 0x85: RES 0, L
+    super::cb_res, // 0x86: RES 0, (HL)
+    super::cb_res, // 0x87: RES 0, A
+    super::cb_res, // 0x88: RES 1, B
+    super::cb_res, // 0x89: RES 1, C
+    super::cb_res, // 0x8a: RES 1, D
+    super::cb_res, // 0x8b: RES 1, E
+    super::cb_res, // 0x8c: RES 1, H
+    super::cb_res, // 0x8d: RES 1, L
+    super::cb_res, // 0x8e: RES 1, (HL)
+    super::cb_res, // 0x8f: RES 1, A
+    super::cb_res, // 0x90: RES 2, B
+    super::cb_res, // 0x91: RES 2, C
+    super::cb_res, // 0x92: RES 2, D
+    super::cb_res, // 0x93: RES 2, E
+    super::cb_res, // 0x94: RES 2, H
+    super::cb_res, // 0x95: RES 2, L
+    super::cb_res, // 0x96: RES 2, (HL)
+    super::cb_res, // 0x97: RES 2, A
+    super::cb_res, // 0x98: RES 3, B
+    super::cb_res, // 0x99: RES 3, C
+    super::cb_res, // 0x9a: RES 3, D
+    super::cb_res, // 0x9b: RES 3, E
+    super::cb_res, // 0x9c: RES 3, H
+    super::cb_res, // 0x9d: RES 3, L
+    super::cb_res, // 0x9e: RES 3, (HL)
+    super::cb_res, // 0x9f: RES 3, A
+    super::cb_res, // 0xa0: RES 4, B
+    super::cb_res, // 0xa1: RES 4, C
+    super::cb_res, // 0xa2: RES 4, D
+    super::cb_res, // 0xa3: RES 4, E
+    super::cb_res, // 0xa4: RES 4, H
+    super::cb_res, // 0xa5: RES 4, L
+    super::cb_res, // 0xa6: RES 4, (HL)
+    super::cb_res, // 0xa7: RES 4, A
+    super::cb_res, // 0xa8: RES 5, B
+    super::cb_res, // 0xa9: RES 5, C
+    super::cb_res, // 0xaa: RES 5, D
+    super::cb_res, // 0xab: RES 5, E
+    super::cb_res, // 0xac: RES 5, H
+    super::cb_res, // 0xad: RES 5, L
+    super::cb_res, // 0xae: RES 5, (HL)
+    super::cb_res, // 0xaf: RES 5, A
+    super::cb_res, // 0xb0: RES 6, B
+    super::cb_res, // 0xb1: RES 6, C
+    super::cb_res, // 0xb2: RES 6, D
+    super::cb_res, // 0xb3: RES 6, E
+    super::cb_res, // 0xb4: RES 6, H
+    super::cb_res, // 0xb5: RES 6, L
+    super::cb_res, // 0xb6: RES 6, (HL)
+    super::cb_res, // 0xb7: RES 6, A
+    super::cb_res, // 0xb8: RES 7, B
+    super::cb_res, // 0xb9: RES 7, C
+    super::cb_res, // 0xba: RES 7, D
+    super::cb_res, // 0xbb: RES 7, E
+    super::cb_res, // 0xbc: RES 7, H
+    super::cb_res, // 0xbd: RES 7, L
+    super::cb_res, // 0xbe: RES 7, (HL)
+    super::cb_res, // 0xbf: RES 7, A
+    super::cb_set, // 0xc0: SET 0, B
+    super::cb_set, // 0xc1: SET 0, C
+    super::cb_set, // 0xc2: SET 0, D
+    super::cb_set, // 0xc3: SET 0, E
+    super::cb_set, // 0xc4: SET 0, H
+    super::cb_set, // 0xc5: SET 0, L
+    super::cb_set, // 0xc6: SET 0, (HL)
+    super::cb_set, // 0xc7: SET 0, A
+    super::cb_set, // 0xc8: SET 1, B
+    super::cb_set, // 0xc9: SET 1, C
+    super::cb_set, // 0xca: SET 1, D
+    super::cb_set, // 0xcb: SET 1, E
+    super::cb_set, // 0xcc: SET 1, H
+    super::cb_set, // 0xcd: SET 1, L
+    super::cb_set, // 0xce: SET 1, (HL)
+    super::cb_set, // 0xcf: SET 1, A
+    super::cb_set, // 0xd0: SET 2, B
+    super::cb_set, // 0xd1: SET 2, C
+    super::cb_set, // 0xd2: SET 2, D
+    super::cb_set, // 0xd3: SET 2, E
+    super::cb_set, // 0xd4: SET 2, H
+    super::cb_set, // 0xd5: SET 2, L
+    super::cb_set, // 0xd6: SET 2, (HL)
+    super::cb_set, // 0xd7: SET 2, A
+    super::cb_set, // 0xd8: SET 3, B
+    super::cb_set, // 0xd9: SET 3, C
+    super::cb_set, // 0xda: SET 3, D
+    super::cb_set, // 0xdb: SET 3, E
+    super::cb_set, // 0xdc: SET 3, H
+    super::cb_set, // 0xdd: SET 3, L
+    super::cb_set, // 0xde: SET 3, (HL)
+    super::cb_set, // 0xdf: SET 3, A
+    super::cb_set, // 0xe0: SET 4, B
+    super::cb_set, // 0xe1: SET 4, C
+    super::cb_set, // 0xe2: SET 4, D
+    super::cb_set, // 0xe3: SET 4, E
+    super::cb_set, // 0xe4: SET 4, H
+    super::cb_set, // 0xe5: SET 4, L
+    super::cb_set, // 0xe6: SET 4, (HL)
+    super::cb_set, // 0xe7: SET 4, A
+    super::cb_set, // 0xe8: SET 5, B
+    super::cb_set, // 0xe9: SET 5, C
+    super::cb_set, // 0xea: SET 5, D
+    super::cb_set, // 0xeb: SET 5, E
+    super::cb_set, // 0xec: SET 5, H
+    super::cb_set, // 0xed: SET 5, L
+    super::cb_set, // 0xee: SET 5, (HL)
+    super::cb_set, // 0xef: SET 5, A
+    super::cb_set, // 0xf0: SET 6, B
+    super::cb_set, // 0xf1: SET 6, C
+    super::cb_set, // 0xf2: SET 6, D
+    super::cb_set, // 0xf3: SET 6, E
+    super::cb_set, // 0xf4: SET 6, H
+    super::cb_set, // 0xf5: SET 6, L
+    super::cb_set, // 0xf6: SET 6, (HL)
+    super::cb_set, // 0xf7: SET 6, A
+    super::cb_set, // 0xf8: SET 7, B
+    super::cb_set, // 0xf9: SET 7, C
+    super::cb_set, // 0xfa: SET 7, D
+    super::cb_set, // 0xfb: SET 7, E
+    super::cb_set, // 0xfc: SET 7, H
+    super::cb_set, // 0xfd: SET 7, L
+    super::cb_set, // 0xfe: SET 7, (HL)
+    super::cb_set, // 0xff: SET 7, A
+];
@@ -0,0 +1,657 @@
+//! The metadata of every instruction: its mnemonic, length, cycles and flag effects.
+
+/// How an instruction affects one of the flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagEffect {
+    Unaffected,
+    Reset,
+    Set,
+    /// Set or reset depending on the result.
+    Affected,
+}
+
+/// What is known about an instruction without executing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    /// The mnemonic, with its immediate operands as the placeholders `u8`, `i8` and `u16`.
+    pub mnemonic: &'static str,
+    /// The length in bytes, including the opcode.
+    pub length: u8,
+    /// The T-cycles taken, when the branch is taken for conditional instructions.
+    pub cycles: u8,
+    /// The T-cycles taken when the branch is not taken, for conditional instructions.
+    pub cycles_not_taken: Option<u8>,
+    /// The effects on the Z, N, H and C flags, in that order.
+    pub flags: [FlagEffect; 4],
+}
+
+/// Returns the metadata of an unconditional instruction.
+/// `flags` holds one of `-`, `0`, `1` or the flag's letter for each of Z, N, H and C.
+const fn op(mnemonic: &'static str, length: u8, cycles: u8, flags: &str) -> Metadata {
+    Metadata { mnemonic, length, cycles, cycles_not_taken: None, flags: flag_effects(flags) }
+}
+
+/// Returns the metadata of a conditional instruction.
+const fn branch(
+    mnemonic: &'static str,
+    length: u8,
+    cycles: u8,
+    cycles_not_taken: u8,
+    flags: &str,
+) -> Metadata {
+    Metadata {
+        cycles_not_taken: Some(cycles_not_taken),
+        ..op(mnemonic, length, cycles, flags)
+    }
+}
+
+/// Parses the flag effects of `op`.
+const fn flag_effects(flags: &str) -> [FlagEffect; 4] {
+    let bytes = flags.as_bytes();
+    let mut effects = [FlagEffect::Unaffected; 4];
+    let mut i = 0;
+    while i < 4 {
+        effects[i] = match bytes[i] {
+            b'-' => FlagEffect::Unaffected,
+            b'0' => FlagEffect::Reset,
+            b'1' => FlagEffect::Set,
+            _ => FlagEffect::Affected,
+        };
+        i += 1;
+    }
+    effects
+}
+
+/// The unprefixed instructions, indexed by their opcode.
+pub static METADATA: [Metadata; 0x100] = [
+    op("NOP", 1, 4, "----"), // 0x00
+    op("LD BC, u16", 3, 12, "----"), // 0x01
+    op("LD (BC), A", 1, 8, "----"), // 0x02
+    op("INC BC", 1, 8, "----"), // 0x03
+    op("INC B", 1, 4, "Z0H-"), // 0x04
+    op("DEC B", 1, 4, "Z1H-"), // 0x05
+    op("LD B, u8", 2, 8, "----"), // 0x06
+    op("RLCA", 1, 4, "000C"), // 0x07
+    op("LD (u16), SP", 3, 20, "----"), // 0x08
+    op("ADD HL, BC", 1, 8, "-0HC"), // 0x09
+    op("LD A, (BC)", 1, 8, "----"), // 0x0a
+    op("DEC BC", 1, 8, "----"), // 0x0b
+    op("INC C", 1, 4, "Z0H-"), // 0x0c
+    op("DEC C", 1, 4, "Z1H-"), // 0x0d
+    op("LD C, u8", 2, 8, "----"), // 0x0e
+    op("RRCA", 1, 4, "000C"), // 0x0f
+    op("STOP", 2, 8, "----"), // 0x10
+    op("LD DE, u16", 3, 12, "----"), // 0x11
+    op("LD (DE), A", 1, 8, "----"), // 0x12
+    op("INC DE", 1, 8, "----"), // 0x13
+    op("INC D", 1, 4, "Z0H-"), // 0x14
+    op("DEC D", 1, 4, "Z1H-"), // 0x15
+    op("LD D, u8", 2, 8, "----"), // 0x16
+    op("RLA", 1, 4, "000C"), // 0x17
+    op("JR i8", 2, 12, "----"), // 0x18
+    op("ADD HL, DE", 1, 8, "-0HC"), // 0x19
+    op("LD A, (DE)", 1, 8, "----"), // 0x1a
+    op("DEC DE", 1, 8, "----"), // 0x1b
+    op("INC E", 1, 4, "Z0H-"), // 0x1c
+    op("DEC E", 1, 4, "Z1H-"), // 0x1d
+    op("LD E, u8", 2, 8, "----"), // 0x1e
+    op("RRA", 1, 4, "000C"), // 0x1f
+    branch("JR NZ, i8", 2, 12, 8, "----"), // 0x20
+    op("LD HL, u16", 3, 12, "----"), // 0x21
+    op("LD (HL+), A", 1, 8, "----"), // 0x22
+    op("INC HL", 1, 8, "----"), // 0x23
+    op("INC H", 1, 4, "Z0H-"), // 0x24
+    op("DEC H", 1, 4, "Z1H-"), // 0x25
+    op("LD H, u8", 2, 8, "----"), // 0x26
+    op("DAA", 1, 4, "Z-0C"), // 0x27
+    branch("JR Z, i8", 2, 12, 8, "----"), // 0x28
+    op("ADD HL, HL", 1, 8, "-0HC"), // 0x29
+    op("LD A, (HL+)", 1, 8, "----"), // 0x2a
+    op("DEC HL", 1, 8, "----"), // 0x2b
+    op("INC L", 1, 4, "Z0H-"), // 0x2c
+    op("DEC L", 1, 4, "Z1H-"), // 0x2d
+    op("LD L, u8", 2, 8, "----"), // 0x2e
+    op("CPL", 1, 4, "-11-"), // 0x2f
+    branch("JR NC, i8", 2, 12, 8, "----"), // 0x30
+    op("LD SP, u16", 3, 12, "----"), // 0x31
+    op("LD (HL-), A", 1, 8, "----"), // 0x32
+    op("INC SP", 1, 8, "----"), // 0x33
+    op("INC (HL)", 1, 12, "Z0H-"), // 0x34
+    op("DEC (HL)", 1, 12, "Z1H-"), // 0x35
+    op("LD (HL), u8", 2, 12, "----"), // 0x36
+    op("SCF", 1, 4, "-001"), // 0x37
+    branch("JR C, i8", 2, 12, 8, "----"), // 0x38
+    op("ADD HL, SP", 1, 8, "-0HC"), // 0x39
+    op("LD A, (HL-)", 1, 8, "----"), // 0x3a
+    op("DEC SP", 1, 8, "----"), // 0x3b
+    op("INC A", 1, 4, "Z0H-"), // 0x3c
+    op("DEC A", 1, 4, "Z1H-"), // 0x3d
+    op("LD A, u8", 2, 8, "----"), // 0x3e
+    op("CCF", 1, 4, "-00C"), // 0x3f
+    op("LD B, B", 1, 4, "----"), // 0x40
+    op("LD B, C", 1, 4, "----"), // 0x41
+    op("LD B, D", 1, 4, "----"), // 0x42
+    op("LD B, E", 1, 4, "----"), // 0x43
+    op("LD B, H", 1, 4, "----"), // 0x44
+    op("LD B, L", 1, 4, "----"), // 0x45
+    op("LD B, (HL)", 1, 8, "----"), // 0x46
+    op("LD B, A", 1, 4, "----"), // 0x47
+    op("LD C, B", 1, 4, "----"), // 0x48
+    op("LD C, C", 1, 4, "----"), // 0x49
+    op("LD C, D", 1, 4, "----"), // 0x4a
+    op("LD C, E", 1, 4, "----"), // 0x4b
+    op("LD C, H", 1, 4, "----"), // 0x4c
+    op("LD C, L", 1, 4, "----"), // 0x4d
+    op("LD C, (HL)", 1, 8, "----"), // 0x4e
+    op("LD C, A", 1, 4, "----"), // 0x4f
+    op("LD D, B", 1, 4, "----"), // 0x50
+    op("LD D, C", 1, 4, "----"), // 0x51
+    op("LD D, D", 1, 4, "----"), // 0x52
+    op("LD D, E", 1, 4, "----"), // 0x53
+    op("LD D, H", 1, 4, "----"), // 0x54
+    op("LD D, L", 1, 4, "----"), // 0x55
+    op("LD D, (HL)", 1, 8, "----"), // 0x56
+    op("LD D, A", 1, 4, "----"), // 0x57
+    op("LD E, B", 1, 4, "----"), // 0x58
+    op("LD E, C", 1, 4, "----"), // 0x59
+    op("LD E, D", 1, 4, "----"), // 0x5a
+    op("LD E, E", 1, 4, "----"), // 0x5b
+    op("LD E, H", 1, 4, "----"), // 0x5c
+    op("LD E, L", 1, 4, "----"), // 0x5d
+    op("LD E, (HL)", 1, 8, "----"), // 0x5e
+    op("LD E, A", 1, 4, "----"), // 0x5f
+    op("LD H, B", 1, 4, "----"), // 0x60
+    op("LD H, C", 1, 4, "----"), // 0x61
+    op("LD H, D", 1, 4, "----"), // 0x62
+    op("LD H, E", 1, 4, "----"), // 0x63
+    op("LD H, H", 1, 4, "----"), // 0x64
+    op("LD H, L", 1, 4, "----"), // 0x65
+    op("LD H, (HL)", 1, 8, "----"), // 0x66
+    op("LD H, A", 1, 4, "----"), // 0x67
+    op("LD L, B", 1, 4, "----"), // 0x68
+    op("LD L, C", 1, 4, "----"), // 0x69
+    op("LD L, D", 1, 4, "----"), // 0x6a
+    op("LD L, E", 1, 4, "----"), // 0x6b
+    op("LD L, H", 1, 4, "----"), // 0x6c
+    op("LD L, L", 1, 4, "----"), // 0x6d
+    op("LD L, (HL)", 1, 8, "----"), // 0x6e
+    op("LD L, A", 1, 4, "----"), // 0x6f
+    op("LD (HL), B", 1, 8, "----"), // 0x70
+    op("LD (HL), C", 1, 8, "----"), // 0x71
+    op("LD (HL), D", 1, 8, "----"), // 0x72
+    op("LD (HL), E", 1, 8, "----"), // 0x73
+    op("LD (HL), H", 1, 8, "----"), // 0x74
+    op("LD (HL), L", 1, 8, "----"), // 0x75
+    op("HALT", 1, 4, "----"), // 0x76
+    op("LD (HL), A", 1, 8, "----"), // 0x77
+    op("LD A, B", 1, 4, "----"), // 0x78
+    op("LD A, C", 1, 4, "----"), // 0x79
+    op("LD A, D", 1, 4, "----"), // 0x7a
+    op("LD A, E", 1, 4, "----"), // 0x7b
+    op("LD A, H", 1, 4, "----"), // 0x7c
+    op("LD A, L", 1, 4, "----"), // 0x7d
+    op("LD A, (HL)", 1, 8, "----"), // 0x7e
+    op("LD A, A", 1, 4, "----"), // 0x7f
+    op("ADD A, B", 1, 4, "Z0HC"), // 0x80
+    op("ADD A, C", 1, 4, "Z0HC"), // 0x81
+    op("ADD A, D", 1, 4, "Z0HC"), // 0x82
+    op("ADD A, E", 1, 4, "Z0HC"), // 0x83
+    op("ADD A, H", 1, 4, "Z0HC"), // 0x84
+    op("ADD A, L", 1, 4, "Z0HC"), // 0x85
+    op("ADD A, (HL)", 1, 8, "Z0HC"), // 0x86
+    op("ADD A, A", 1, 4, "Z0HC"), // 0x87
+    op("ADC A, B", 1, 4, "Z0HC"), // 0x88
+    op("ADC A, C", 1, 4, "Z0HC"), // 0x89
+    op("ADC A, D", 1, 4, "Z0HC"), // 0x8a
+    op("ADC A, E", 1, 4, "Z0HC"), // 0x8b
+    op("ADC A, H", 1, 4, "Z0HC"), // 0x8c
+    op("ADC A, L", 1, 4, "Z0HC"), // 0x8d
+    op("ADC A, (HL)", 1, 8, "Z0HC"), // 0x8e
+    op("ADC A, A", 1, 4, "Z0HC"), // 0x8f
+    op("SUB A, B", 1, 4, "Z1HC"), // 0x90
+    op("SUB A, C", 1, 4, "Z1HC"), // 0x91
+    op("SUB A, D", 1, 4, "Z1HC"), // 0x92
+    op("SUB A, E", 1, 4, "Z1HC"), // 0x93
+    op("SUB A, H", 1, 4, "Z1HC"), // 0x94
+    op("SUB A, L", 1, 4, "Z1HC"), // 0x95
+    op("SUB A, (HL)", 1, 8, "Z1HC"), // 0x96
+    op("SUB A, A", 1, 4, "Z1HC"), // 0x97
+    op("SBC A, B", 1, 4, "Z1HC"), // 0x98
+    op("SBC A, C", 1, 4, "Z1HC"), // 0x99
+    op("SBC A, D", 1, 4, "Z1HC"), // 0x9a
+    op("SBC A, E", 1, 4, "Z1HC"), // 0x9b
+    op("SBC A, H", 1, 4, "Z1HC"), // 0x9c
+    op("SBC A, L", 1, 4, "Z1HC"), // 0x9d
+    op("SBC A, (HL)", 1, 8, "Z1HC"), // 0x9e
+    op("SBC A, A", 1, 4, "Z1HC"), // 0x9f
+    op("AND A, B", 1, 4, "Z010"), // 0xa0
+    op("AND A, C", 1, 4, "Z010"), // 0xa1
+    op("AND A, D", 1, 4, "Z010"), // 0xa2
+    op("AND A, E", 1, 4, "Z010"), // 0xa3
+    op("AND A, H", 1, 4, "Z010"), // 0xa4
+    op("AND A, L", 1, 4, "Z010"), // 0xa5
+    op("AND A, (HL)", 1, 8, "Z010"), // 0xa6
+    op("AND A, A", 1, 4, "Z010"), // 0xa7
+    op("XOR A, B", 1, 4, "Z000"), // 0xa8
+    op("XOR A, C", 1, 4, "Z000"), // 0xa9
+    op("XOR A, D", 1, 4, "Z000"), // 0xaa
+    op("XOR A, E", 1, 4, "Z000"), // 0xab
+    op("XOR A, H", 1, 4, "Z000"), // 0xac
+    op("XOR A, L", 1, 4, "Z000"), // 0xad
+    op("XOR A, (HL)", 1, 8, "Z000"), // 0xae
+    op("XOR A, A", 1, 4, "Z000"), // 0xaf
+    op("OR A, B", 1, 4, "Z000"), // 0xb0
+    op("OR A, C", 1, 4, "Z000"), // 0xb1
+    op("OR A, D", 1, 4, "Z000"), // 0xb2
+    op("OR A, E", 1, 4, "Z000"), // 0xb3
+    op("OR A, H", 1, 4, "Z000"), // 0xb4
+    op("OR A, L", 1, 4, "Z000"), // 0xb5
+    op("OR A, (HL)", 1, 8, "Z000"), // 0xb6
+    op("OR A, A", 1, 4, "Z000"), // 0xb7
+    op("CP A, B", 1, 4, "Z1HC"), // 0xb8
+    op("CP A, C", 1, 4, "Z1HC"), // 0xb9
+    op("CP A, D", 1, 4, "Z1HC"), // 0xba
+    op("CP A, E", 1, 4, "Z1HC"), // 0xbb
+    op("CP A, H", 1, 4, "Z1HC"), // 0xbc
+    op("CP A, L", 1, 4, "Z1HC"), // 0xbd
+    op("CP A, (HL)", 1, 8, "Z1HC"), // 0xbe
+    op("CP A, A", 1, 4, "Z1HC"), // 0xbf
+    branch("RET NZ", 1, 20, 8, "----"), // 0xc0
+    op("POP BC", 1, 12, "----"), // 0xc1
+    branch("JP NZ, u16", 3, 16, 12, "----"), // 0xc2
+    op("JP u16", 3, 16, "----"), // 0xc3
+    branch("CALL NZ, u16", 3, 24, 12, "----"), // 0xc4
+    op("PUSH BC", 1, 16, "----"), // 0xc5
+    op("ADD A, u8", 2, 8, "Z0HC"), // 0xc6
+    op("RST 00h", 1, 16, "----"), // 0xc7
+    branch("RET Z", 1, 20, 8, "----"), // 0xc8
+    op("RET", 1, 16, "----"), // 0xc9
+    branch("JP Z, u16", 3, 16, 12, "----"), // 0xca
+    op("PREFIX CB", 1, 4, "----"), // 0xcb
+    branch("CALL Z, u16", 3, 24, 12, "----"), // 0xcc
+    op("CALL u16", 3, 24, "----"), // 0xcd
+    op("ADC A, u8", 2, 8, "Z0HC"), // 0xce
+    op("RST 08h", 1, 16, "----"), // 0xcf
+    branch("RET NC", 1, 20, 8, "----"), // 0xd0
+    op("POP DE", 1, 12, "----"), // 0xd1
+    branch("JP NC, u16", 3, 16, 12, "----"), // 0xd2
+    op("ILLEGAL", 1, 4, "----"), // 0xd3
+    branch("CALL NC, u16", 3, 24, 12, "----"), // 0xd4
+    op("PUSH DE", 1, 16, "----"), // 0xd5
+    op("SUB A, u8", 2, 8, "Z1HC"), // 0xd6
+    op("RST 10h", 1, 16, "----"), // 0xd7
+    branch("RET C", 1, 20, 8, "----"), // 0xd8
+    op("RETI", 1, 16, "----"), // 0xd9
+    branch("JP C, u16", 3, 16, 12, "----"), // 0xda
+    op("ILLEGAL", 1, 4, "----"), // 0xdb
+    branch("CALL C, u16", 3, 24, 12, "----"), // 0xdc
+    op("ILLEGAL", 1, 4, "----"), // 0xdd
+    op("SBC A, u8", 2, 8, "Z1HC"), // 0xde
+    op("RST 18h", 1, 16, "----"), // 0xdf
+    op("LD (FF00+u8), A", 2, 12, "----"), // 0xe0
+    op("POP HL", 1, 12, "----"), // 0xe1
+    op("LD (FF00+C), A", 1, 8, "----"), // 0xe2
+    op("ILLEGAL", 1, 4, "----"), // 0xe3
+    op("ILLEGAL", 1, 4, "----"), // 0xe4
+    op("PUSH HL", 1, 16, "----"), // 0xe5
+    op("AND A, u8", 2, 8, "Z010"), // 0xe6
+    op("RST 20h", 1, 16, "----"), // 0xe7
+    op("ADD SP, i8", 2, 16, "00HC"), // 0xe8
+    op("JP HL", 1, 4, "----"), // 0xe9
+    op("LD (u16), A", 3, 16, "----"), // 0xea
+    op("ILLEGAL", 1, 4, "----"), // 0xeb
+    op("ILLEGAL", 1, 4, "----"), // 0xec
+    op("ILLEGAL", 1, 4, "----"), // 0xed
+    op("XOR A, u8", 2, 8, "Z000"), // 0xee
+    op("RST 28h", 1, 16, "----"), // 0xef
+    op("LD A, (FF00+u8)", 2, 12, "----"), // 0xf0
+    op("POP AF", 1, 12, "ZNHC"), // 0xf1
+    op("LD A, (FF00+C)", 1, 8, "----"), // 0xf2
+    op("DI", 1, 4, "----"), // 0xf3
+    op("ILLEGAL", 1, 4, "----"), // 0xf4
+    op("PUSH AF", 1, 16, "----"), // 0xf5
+    op("OR A, u8", 2, 8, "Z000"), // 0xf6
+    op("RST 30h", 1, 16, "----"), // 0xf7
+    op("LD HL, SP+i8", 2, 12, "00HC"), // 0xf8
+    op("LD SP, HL", 1, 8, "----"), // 0xf9
+    op("LD A, (u16)", 3, 16, "----"), // 0xfa
+    op("EI", 1, 4, "----"), // 0xfb
+    op("ILLEGAL", 1, 4, "----"), // 0xfc
+    op("ILLEGAL", 1, 4, "----"), // 0xfd
+    op("CP A, u8", 2, 8, "Z1HC"), // 0xfe
+    op("RST 38h", 1, 16, "----"), // 0xff
+];
+
+/// The CB-prefixed instructions, indexed by the opcode following the prefix.
+/// Their length and cycles include the prefix.
+pub static METADATA_PREFIX: [Metadata; 0x100] = [
+    op("RLC B", 2, 8, "Z00C"), // 0x00
+    op("RLC C", 2, 8, "Z00C"), // 0x01
+    op("RLC D", 2, 8, "Z00C"), // 0x02
+    op("RLC E", 2, 8, "Z00C"), // 0x03
+    op("RLC H", 2, 8, "Z00C"), // 0x04
+    op("RLC L", 2, 8, "Z00C"), // 0x05
+    op("RLC (HL)", 2, 16, "Z00C"), // 0x06
+    op("RLC A", 2, 8, "Z00C"), // 0x07
+    op("RRC B", 2, 8, "Z00C"), // 0x08
+    op("RRC C", 2, 8, "Z00C"), // 0x09
+    op("RRC D", 2, 8, "Z00C"), // 0x0a
+    op("RRC E", 2, 8, "Z00C"), // 0x0b
+    op("RRC H", 2, 8, "Z00C"), // 0x0c
+    op("RRC L", 2, 8, "Z00C"), // 0x0d
+    op("RRC (HL)", 2, 16, "Z00C"), // 0x0e
+    op("RRC A", 2, 8, "Z00C"), // 0x0f
+    op("RL B", 2, 8, "Z00C"), // 0x10
+    op("RL C", 2, 8, "Z00C"), // 0x11
+    op("RL D", 2, 8, "Z00C"), // 0x12
+    op("RL E", 2, 8, "Z00C"), // 0x13
+    op("RL H", 2, 8, "Z00C"), // 0x14
+    op("RL L", 2, 8, "Z00C"), // 0x15
+    op("RL (HL)", 2, 16, "Z00C"), // 0x16
+    op("RL A", 2, 8, "Z00C"), // 0x17
+    op("RR B", 2, 8, "Z00C"), // 0x18
+    op("RR C", 2, 8, "Z00C"), // 0x19
+    op("RR D", 2, 8, "Z00C"), // 0x1a
+    op("RR E", 2, 8, "Z00C"), // 0x1b
+    op("RR H", 2, 8, "Z00C"), // 0x1c
+    op("RR L", 2, 8, "Z00C"), // 0x1d
+    op("RR (HL)", 2, 16, "Z00C"), // 0x1e
+    op("RR A", 2, 8, "Z00C"), // 0x1f
+    op("SLA B", 2, 8, "Z00C"), // 0x20
+    op("SLA C", 2, 8, "Z00C"), // 0x21
+    op("SLA D", 2, 8, "Z00C"), // 0x22
+    op("SLA E", 2, 8, "Z00C"), // 0x23
+    op("SLA H", 2, 8, "Z00C"), // 0x24
+    op("SLA L", 2, 8, "Z00C"), // 0x25
+    op("SLA (HL)", 2, 16, "Z00C"), // 0x26
+    op("SLA A", 2, 8, "Z00C"), // 0x27
+    op("SRA B", 2, 8, "Z00C"), // 0x28
+    op("SRA C", 2, 8, "Z00C"), // 0x29
+    op("SRA D", 2, 8, "Z00C"), // 0x2a
+    op("SRA E", 2, 8, "Z00C"), // 0x2b
+    op("SRA H", 2, 8, "Z00C"), // 0x2c
+    op("SRA L", 2, 8, "Z00C"), // 0x2d
+    op("SRA (HL)", 2, 16, "Z00C"), // 0x2e
+    op("SRA A", 2, 8, "Z00C"), // 0x2f
+    op("SWAP B", 2, 8, "Z000"), // 0x30
+    op("SWAP C", 2, 8, "Z000"), // 0x31
+    op("SWAP D", 2, 8, "Z000"), // 0x32
+    op("SWAP E", 2, 8, "Z000"), // 0x33
+    op("SWAP H", 2, 8, "Z000"), // 0x34
+    op("SWAP L", 2, 8, "Z000"), // 0x35
+    op("SWAP (HL)", 2, 16, "Z000"), // 0x36
+    op("SWAP A", 2, 8, "Z000"), // 0x37
+    op("SRL B", 2, 8, "Z00C"), // 0x38
+    op("SRL C", 2, 8, "Z00C"), // 0x39
+    op("SRL D", 2, 8, "Z00C"), // 0x3a
+    op("SRL E", 2, 8, "Z00C"), // 0x3b
+    op("SRL H", 2, 8, "Z00C"), // 0x3c
+    op("SRL L", 2, 8, "Z00C"), // 0x3d
+    op("SRL (HL)", 2, 16, "Z00C"), // 0x3e
+    op("SRL A", 2, 8, "Z00C"), // 0x3f
+    op("BIT 0, B", 2, 8, "Z01-"), // 0x40
+    op("BIT 0, C", 2, 8, "Z01-"), // 0x41
+    op("BIT 0, D", 2, 8, "Z01-"), // 0x42
+    op("BIT 0, E", 2, 8, "Z01-"), // 0x43
+    op("BIT 0, H", 2, 8, "Z01-"), // 0x44
+    op("BIT 0, L", 2, 8, "Z01-"), // 0x45
+    op("BIT 0, (HL)", 2, 12, "Z01-"), // 0x46
+    op("BIT 0, A", 2, 8, "Z01-"), // 0x47
+    op("BIT 1, B", 2, 8, "Z01-"), // 0x48
+    op("BIT 1, C", 2, 8, "Z01-"), // 0x49
+    op("BIT 1, D", 2, 8, "Z01-"), // 0x4a
+    op("BIT 1, E", 2, 8, "Z01-"), // 0x4b
+    op("BIT 1, H", 2, 8, "Z01-"), // 0x4c
+    op("BIT 1, L", 2, 8, "Z01-"), // 0x4d
+    op("BIT 1, (HL)", 2, 12, "Z01-"), // 0x4e
+    op("BIT 1, A", 2, 8, "Z01-"), // 0x4f
+    op("BIT 2, B", 2, 8, "Z01-"), // 0x50
+    op("BIT 2, C", 2, 8, "Z01-"), // 0x51
+    op("BIT 2, D", 2, 8, "Z01-"), // 0x52
+    op("BIT 2, E", 2, 8, "Z01-"), // 0x53
+    op("BIT 2, H", 2, 8, "Z01-"), // 0x54
+    op("BIT 2, L", 2, 8, "Z01-"), // 0x55
+    op("BIT 2, (HL)", 2, 12, "Z01-"), // 0x56
+    op("BIT 2, A", 2, 8, "Z01-"), // 0x57
+    op("BIT 3, B", 2, 8, "Z01-"), // 0x58
+    op("BIT 3, C", 2, 8, "Z01-"), // 0x59
+    op("BIT 3, D", 2, 8, "Z01-"), // 0x5a
+    op("BIT 3, E", 2, 8, "Z01-"), // 0x5b
+    op("BIT 3, H", 2, 8, "Z01-"), // 0x5c
+    op("BIT 3, L", 2, 8, "Z01-"), // 0x5d
+    op("BIT 3, (HL)", 2, 12, "Z01-"), // 0x5e
+    op("BIT 3, A", 2, 8, "Z01-"), // 0x5f
+    op("BIT 4, B", 2, 8, "Z01-"), // 0x60
+    op("BIT 4, C", 2, 8, "Z01-"), // 0x61
+    op("BIT 4, D", 2, 8, "Z01-"), // 0x62
+    op("BIT 4, E", 2, 8, "Z01-"), // 0x63
+    op("BIT 4, H", 2, 8, "Z01-"), // 0x64
+    op("BIT 4, L", 2, 8, "Z01-"), // 0x65
+    op("BIT 4, (HL)", 2, 12, "Z01-"), // 0x66
+    op("BIT 4, A", 2, 8, "Z01-"), // 0x67
+    op("BIT 5, B", 2, 8, "Z01-"), // 0x68
+    op("BIT 5, C", 2, 8, "Z01-"), // 0x69
+    op("BIT 5, D", 2, 8, "Z01-"), // 0x6a
+    op("BIT 5, E", 2, 8, "Z01-"), // 0x6b
+    op("BIT 5, H", 2, 8, "Z01-"), // 0x6c
+    op("BIT 5, L", 2, 8, "Z01-"), // 0x6d
+    op("BIT 5, (HL)", 2, 12, "Z01-"), // 0x6e
+    op("BIT 5, A", 2, 8, "Z01-"), // 0x6f
+    op("BIT 6, B", 2, 8, "Z01-"), // 0x70
+    op("BIT 6, C", 2, 8, "Z01-"), // 0x71
+    op("BIT 6, D", 2, 8, "Z01-"), // 0x72
+    op("BIT 6, E", 2, 8, "Z01-"), // 0x73
+    op("BIT 6, H", 2, 8, "Z01-"), // 0x74
+    op("BIT 6, L", 2, 8, "Z01-"), // 0x75
+    op("BIT 6, (HL)", 2, 12, "Z01-"), // 0x76
+    op("BIT 6, A", 2, 8, "Z01-"), // 0x77
+    op("BIT 7, B", 2, 8, "Z01-"), // 0x78
+    op("BIT 7, C", 2, 8, "Z01-"), // 0x79
+    op("BIT 7, D", 2, 8, "Z01-"), // 0x7a
+    op("BIT 7, E", 2, 8, "Z01-"), // 0x7b
+    op("BIT 7, H", 2, 8, "Z01-"), // 0x7c
+    op("BIT 7, L", 2, 8, "Z01-"), // 0x7d
+    op("BIT 7, (HL)", 2, 12, "Z01-"), // 0x7e
+    op("BIT 7, A", 2, 8, "Z01-"), // 0x7f
+    op("RES 0, B", 2, 8, "----"), // 0x80
+    op("RES 0, C", 2, 8, "----"), // 0x81
+    op("RES 0, D", 2, 8, "----"), // 0x82
+    op("RES 0, E", 2, 8, "----"), // 0x83
+    op("RES 0, H", 2, 8, "----"), // 0x84
+    op("RES 0, L", 2, 8, "----"), // 0x85
+    op("RES 0, (HL)", 2, 16, "----"), // 0x86
+    op("RES 0, A", 2, 8, "----"), // 0x87
+    op("RES 1, B", 2, 8, "----"), // 0x88
+    op("RES 1, C", 2, 8, "----"), // 0x89
+    op("RES 1, D", 2, 8, "----"), // 0x8a
+    op("RES 1, E", 2, 8, "----"), // 0x8b
+    op("RES 1, H", 2, 8, "----"), // 0x8c
+    op("RES 1, L", 2, 8, "----"), // 0x8d
+    op("RES 1, (HL)", 2, 16, "----"), // 0x8e
+    op("RES 1, A", 2, 8, "----"), // 0x8f
+    op("RES 2, B", 2, 8, "----"), // 0x90
+    op("RES 2, C", 2, 8, "----"), // 0x91
+    op("RES 2, D", 2, 8, "----"), // 0x92
+    op("RES 2, E", 2, 8, "----"), // 0x93
+    op("RES 2, H", 2, 8, "----"), // 0x94
+    op("RES 2, L", 2, 8, "----"), // 0x95
+    op("RES 2, (HL)", 2, 16, "----"), // 0x96
+    op("RES 2, A", 2, 8, "----"), // 0x97
+    op("RES 3, B", 2, 8, "----"), // 0x98
+    op("RES 3, C", 2, 8, "----"), // 0x99
+    op("RES 3, D", 2, 8, "----"), // 0x9a
+    op("RES 3, E", 2, 8, "----"), // 0x9b
+    op("RES 3, H", 2, 8, "----"), // 0x9c
+    op("RES 3, L", 2, 8, "----"), // 0x9d
+    op("RES 3, (HL)", 2, 16, "----"), // 0x9e
+    op("RES 3, A", 2, 8, "----"), // 0x9f
+    op("RES 4, B", 2, 8, "----"), // 0xa0
+    op("RES 4, C", 2, 8, "----"), // 0xa1
+    op("RES 4, D", 2, 8, "----"), // 0xa2
+    op("RES 4, E", 2, 8, "----"), // 0xa3
+    op("RES 4, H", 2, 8, "----"), // 0xa4
+    op("RES 4, L", 2, 8, "----"), // 0xa5
+    op("RES 4, (HL)", 2, 16, "----"), // 0xa6
+    op("RES 4, A", 2, 8, "----"), // 0xa7
+    op("RES 5, B", 2, 8, "----"), // 0xa8
+    op("RES 5, C", 2, 8, "----"), // 0xa9
+    op("RES 5, D", 2, 8, "----"), // 0xaa
+    op("RES 5, E", 2, 8, "----"), // 0xab
+    op("RES 5, H", 2, 8, "----"), // 0xac
+    op("RES 5, L", 2, 8, "----"), // 0xad
+    op("RES 5, (HL)", 2, 16, "----"), // 0xae
+    op("RES 5, A", 2, 8, "----"), // 0xaf
+    op("RES 6, B", 2, 8, "----"), // 0xb0
+    op("RES 6, C", 2, 8, "----"), // 0xb1
+    op("RES 6, D", 2, 8, "----"), // 0xb2
+    op("RES 6, E", 2, 8, "----"), // 0xb3
+    op("RES 6, H", 2, 8, "----"), // 0xb4
+    op("RES 6, L", 2, 8, "----"), // 0xb5
+    op("RES 6, (HL)", 2, 16, "----"), // 0xb6
+    op("RES 6, A", 2, 8, "----"), // 0xb7
+    op("RES 7, B", 2, 8, "----"), // 0xb8
+    op("RES 7, C", 2, 8, "----"), // 0xb9
+    op("RES 7, D", 2, 8, "----"), // 0xba
+    op("RES 7, E", 2, 8, "----"), // 0xbb
+    op("RES 7, H", 2, 8, "----"), // 0xbc
+    op("RES 7, L", 2, 8, "----"), // 0xbd
+    op("RES 7, (HL)", 2, 16, "----"), // 0xbe
+    op("RES 7, A", 2, 8, "----"), // 0xbf
+    op("SET 0, B", 2, 8, "----"), // 0xc0
+    op("SET 0, C", 2, 8, "----"), // 0xc1
+    op("SET 0, D", 2, 8, "----"), // 0xc2
+    op("SET 0, E", 2, 8, "----"), // 0xc3
+    op("SET 0, H", 2, 8, "----"), // 0xc4
+    op("SET 0, L", 2, 8, "----"), // 0xc5
+    op("SET 0, (HL)", 2, 16, "----"), // 0xc6
+    op("SET 0, A", 2, 8, "----"), // 0xc7
+    op("SET 1, B", 2, 8, "----"), // 0xc8
+    op("SET 1, C", 2, 8, "----"), // 0xc9
+    op("SET 1, D", 2, 8, "----"), // 0xca
+    op("SET 1, E", 2, 8, "----"), // 0xcb
+    op("SET 1, H", 2, 8, "----"), // 0xcc
+    op("SET 1, L", 2, 8, "----"), // 0xcd
+    op("SET 1, (HL)", 2, 16, "----"), // 0xce
+    op("SET 1, A", 2, 8, "----"), // 0xcf
+    op("SET 2, B", 2, 8, "----"), // 0xd0
+    op("SET 2, C", 2, 8, "----"), // 0xd1
+    op("SET 2, D", 2, 8, "----"), // 0xd2
+    op("SET 2, E", 2, 8, "----"), // 0xd3
+    op("SET 2, H", 2, 8, "----"), // 0xd4
+    op("SET 2, L", 2, 8, "----"), // 0xd5
+    op("SET 2, (HL)", 2, 16, "----"), // 0xd6
+    op("SET 2, A", 2, 8, "----"), // 0xd7
+    op("SET 3, B", 2, 8, "----"), // 0xd8
+    op("SET 3, C", 2, 8, "----"), // 0xd9
+    op("SET 3, D", 2, 8, "----"), // 0xda
+    op("SET 3, E", 2, 8, "----"), // 0xdb
+    op("SET 3, H", 2, 8, "----"), // 0xdc
+    op("SET 3, L", 2, 8, "----"), // 0xdd
+    op("SET 3, (HL)", 2, 16, "----"), // 0xde
+    op("SET 3, A", 2, 8, "----"), // 0xdf
+    op("SET 4, B", 2, 8, "----"), // 0xe0
+    op("SET 4, C", 2, 8, "----"), // 0xe1
+    op("SET 4, D", 2, 8, "----"), // 0xe2
+    op("SET 4, E", 2, 8, "----"), // 0xe3
+    op("SET 4, H", 2, 8, "----"), // 0xe4
+    op("SET 4, L", 2, 8, "----"), // 0xe5
+    op("SET 4, (HL)", 2, 16, "----"), // 0xe6
+    op("SET 4, A", 2, 8, "----"), // 0xe7
+    op("SET 5, B", 2, 8, "----"), // 0xe8
+    op("SET 5, C", 2, 8, "----"), // 0xe9
+    op("SET 5, D", 2, 8, "----"), // 0xea
+    op("SET 5, E", 2, 8, "----"), // 0xeb
+    op("SET 5, H", 2, 8, "----"), // 0xec
+    op("SET 5, L", 2, 8, "----"), // 0xed
+    op("SET 5, (HL)", 2, 16, "----"), // 0xee
+    op("SET 5, A", 2, 8, "----"), // 0xef
+    op("SET 6, B", 2, 8, "----"), // 0xf0
+    op("SET 6, C", 2, 8, "----"), // 0xf1
+    op("SET 6, D", 2, 8, "----"), // 0xf2
+    op("SET 6, E", 2, 8, "----"), // 0xf3
+    op("SET 6, H", 2, 8, "----"), // 0xf4
+    op("SET 6, L", 2, 8, "----"), // 0xf5
+    op("SET 6, (HL)", 2, 16, "----"), // 0xf6
+    op("SET 6, A", 2, 8, "----"), // 0xf7
+    op("SET 7, B", 2, 8, "----"), // 0xf8
+    op("SET 7, C", 2, 8, "----"), // 0xf9
+    op("SET 7, D", 2, 8, "----"), // 0xfa
+    op("SET 7, E", 2, 8, "----"), // 0xfb
+    op("SET 7, H", 2, 8, "----"), // 0xfc
+    op("SET 7, L", 2, 8, "----"), // 0xfd
+    op("SET 7, (HL)", 2, 16, "----"), // 0xfe
+    op("SET 7, A", 2, 8, "----"), // 0xff
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::registers::{ Flags, Reg16 };
+    use crate::cpu::Cpu;
+    use crate::memory::Memory;
+
+    /// Executes `program` at 0x0150 with the flags `f`.
+    /// Returns the T-cycles taken, the bytes the PC advanced by and the new flags.
+    fn execute(program: &[u8], f: u8) -> (u8, u16, u8) {
+        let mut cpu = Cpu::new();
+        let mut memory = Memory::new();
+        for (i, &byte) in program.iter().enumerate() {
+            memory.write_byte(0x0150 + i as u16, byte);
+        }
+        cpu.set_pc(0x0150);
+        cpu.set_reg16(Reg16::SP, 0xc000);
+        cpu.set_reg16(Reg16::AF, f as u16);
+
+        // Illegal opcodes are an error, but still take their cycles.
+        let _ = cpu.step(&mut memory);
+        let state = cpu.state();
+        (state.cycles as u8, state.pc.wrapping_sub(0x0150), state.f)
+    }
+
+    /// Checks the cycles, length and flags of the instruction encoded by `program`.
+    fn check(metadata: &Metadata, program: &[u8]) {
+        let mut cycles_seen = Vec::new();
+        // NZ and NC hold with the first flags, Z and C with the second.
+        for f in [0x00, 0xf0] {
+            let (cycles, advanced, new_f) = execute(program, f);
+            cycles_seen.push(cycles);
+
+            let is_jump = ["JP", "JR", "CALL", "RET", "RST"]
+                .iter()
+                .any(|jump| metadata.mnemonic.starts_with(jump));
+            if !is_jump {
+                assert_eq!(advanced, metadata.length as u16, "length of {}", metadata.mnemonic);
+            }
+
+            let masks = [Flags::Z, Flags::N, Flags::H, Flags::C];
+            for (effect, mask) in metadata.flags.iter().zip(masks) {
+                let expected = match effect {
+                    FlagEffect::Unaffected => f & mask,
+                    FlagEffect::Reset => 0,
+                    FlagEffect::Set => mask,
+                    FlagEffect::Affected => continue,
+                };
+                assert_eq!(new_f & mask, expected, "flag {:#04x} of {}", mask, metadata.mnemonic);
+            }
+        }
+
+        let mut expected = vec![metadata.cycles];
+        expected.extend(metadata.cycles_not_taken);
+        cycles_seen.sort_unstable();
+        cycles_seen.dedup();
+        expected.sort_unstable();
+        assert_eq!(cycles_seen, expected, "cycles of {}", metadata.mnemonic);
+    }
+
+    #[test]
+    fn every_instruction_matches_its_metadata() {
+        for opcode in 0..=0xff {
+            if opcode != 0xcb {
+                check(&METADATA[opcode as usize], &[opcode, 0x00, 0x00]);
+            }
+        }
+        for opcode in 0..=0xff {
+            check(&METADATA_PREFIX[opcode as usize], &[0xcb, opcode]);
+        }
+    }
+}
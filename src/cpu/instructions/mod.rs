@@ -1,826 +1,702 @@
 use crate::memory::Memory;
 use crate::cpu::{ 
+    alu,
     registers::{ 
         Flags, 
-        Regs,
+        Reg8,
+        Reg16,
     },
     Cpu, 
+    RunState,
 };
+use crate::timer::DIV;
 
 pub mod lookup;
+pub mod metadata;
 
-/// Returns a tuple of the incremented value of `register` and the modified flags.
-fn dec_reg(register: u8) -> (u8, u8) {
-    let new_value = register.wrapping_sub(1);
-    let mut new_flags = 0;
-    if new_value == 0 {
-        new_flags |= Flags::Z;
-    }
+/* OPCODE FIELDS */
 
-    new_flags |= Flags::N;
+// Opcodes are decoded from the bit fields xxyyyzzz, where y is further split into ppq.
+// See `gameboy_things/SM83_decoding.pdf` for which fields each instruction uses.
 
-    // Only if the register value's lowest set bit is the 4th bit will it overflow 
-    // (backwards) from bit 4 when subtracting 1.
-    if register & 0b11111 == 0b10000 {
-        // Bit 4 overflowed (backwards).
-        new_flags |= Flags::H;
-    }
-    
-    (new_value, new_flags)
+/// Returns the y field (bits 3-5) of `opcode`.
+fn y(opcode: u8) -> u8 {
+    (opcode >> 3) & 0b111
 }
 
-/* PREFIX INSTRUCTIONS */
-
-/// Handles the prefixed instructions.
-pub fn op_cb(cpu: &mut Cpu, memory: &mut Memory) {
-    use lookup::{ InstructionAccess, INSTRS_PREFIX };
-
-    let opcode = cpu.consume_byte(memory);
-    if let Some(instr_access) = INSTRS_PREFIX.get(&opcode) {
-        match instr_access {
-            InstructionAccess::Cpu(instr) => instr(cpu),
-            InstructionAccess::CpuWithMemory(instr) => instr(cpu, memory),
-        }
-    } else {
-        todo!("{:#04x}", opcode);
-    }
+/// Returns the z field (bits 0-2) of `opcode`.
+fn z(opcode: u8) -> u8 {
+    opcode & 0b111
+}
 
-    cpu.div_ctrl += 4; // fetching the prefix
+/// Returns the p field (bits 4-5) of `opcode`.
+fn p(opcode: u8) -> u8 {
+    (opcode >> 4) & 0b11
 }
 
-/// RLC B
-/// Shifts register B by one bit to the left.
-pub fn op_cb00(cpu: &mut Cpu) {
-    let b = cpu.regs.b();
-    if b & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
-    }
-    cpu.regs.set_b(b.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+/// An 8 bit operand, as encoded in the `r` fields of an opcode.
+#[derive(Clone, Copy)]
+enum Operand {
+    Reg(Reg8),
+    /// The byte at memory location pointed to by register HL.
+    IndirectHl,
 }
 
-/// RLC C
-/// Shifts register C by one bit to the left.
-pub fn op_cb01(cpu: &mut Cpu) {
-    let c = cpu.regs.c();
-    if c & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
+impl Operand {
+    /// Decodes the 3 bit `r` field `bits`.
+    fn decode(bits: u8) -> Self {
+        match Reg8::from_r(bits) {
+            Some(reg) => Operand::Reg(reg),
+            None => Operand::IndirectHl,
+        }
     }
-    cpu.regs.set_c(c.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
 
-/// RLC D
-/// Shifts register D by one bit to the left.
-pub fn op_cb02(cpu: &mut Cpu) {
-    let d = cpu.regs.d();
-    if d & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
+    fn read(self, cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+        match self {
+            Operand::Reg(reg) => cpu.regs.reg8(reg),
+            Operand::IndirectHl => cpu.read(memory, cpu.regs.hl()),
+        }
     }
-    cpu.regs.set_d(d.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
 
-/// RLC E
-/// Shifts register E by one bit to the left.
-pub fn op_cb03(cpu: &mut Cpu) {
-    let e = cpu.regs.e();
-    if e & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
+    fn write(self, cpu: &mut Cpu, memory: &mut Memory, value: u8) {
+        match self {
+            Operand::Reg(reg) => cpu.regs.set_reg8(reg, value),
+            Operand::IndirectHl => cpu.write(memory, cpu.regs.hl(), value),
+        }
     }
-    cpu.regs.set_e(e.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
 
-/// RLC H
-/// Shifts register H by one bit to the left.
-pub fn op_cb04(cpu: &mut Cpu) {
-    let h = cpu.regs.h();
-    if h & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
+    /// Returns the number of extra cycles needed for every access to the operand.
+    fn access_cycles(self) -> u8 {
+        match self {
+            Operand::Reg(_) => 0,
+            Operand::IndirectHl => 4,
+        }
     }
-    cpu.regs.set_h(h.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
 }
 
-/// RLC L
-/// Shifts register L by one bit to the left.
-pub fn op_cb05(cpu: &mut Cpu) {
-    let l = cpu.regs.l();
-    if l & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
+/// Returns whether the 2 bit condition field `bits` (NZ, Z, NC, C) holds.
+fn condition(cpu: &Cpu, bits: u8) -> bool {
+    match bits & 0b11 {
+        0 => !cpu.regs.check_flags(Flags::Z),
+        1 => cpu.regs.check_flags(Flags::Z),
+        2 => !cpu.regs.check_flags(Flags::C),
+        _ => cpu.regs.check_flags(Flags::C),
     }
-    cpu.regs.set_l(l.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
 }
 
-/// RLC (HL)
-/// Shifts the byte at memory location pointed to by register HL by one bit to the left.
-pub fn op_cb06(cpu: &mut Cpu, memory: &mut Memory) {
+/* HELPERS */
+
+/// ADD HL, `value`.
+/// The Z flag is not modified.
+fn add_hl(cpu: &mut Cpu, value: u16) {
     let hl = cpu.regs.hl();
-    let byte = memory.read_byte(hl as usize);
-    if byte & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
-    }
-    memory.write_byte(hl as usize, byte.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
+    let (sum, carry) = hl.overflowing_add(value);
 
-/// RLC A
-/// Shifts register A by one bit to the left.
-pub fn op_cb07(cpu: &mut Cpu) {
-    let a = cpu.regs.a();
-    if a & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
+    let mut flags = cpu.regs.f() & Flags::Z;
+    if (hl & 0x0fff) + (value & 0x0fff) > 0x0fff { // Carry from bit 11
+        flags |= Flags::H;
     }
-    cpu.regs.set_a(a.rotate_left(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
-
-/// RRC B
-/// Shifts register B by one bit to the right.
-pub fn op_cb08(cpu: &mut Cpu) {
-    let b = cpu.regs.b();
-    if b & 1 == 1 {
-        cpu.regs.set_flags(Flags::C);
+    if carry {
+        flags |= Flags::C;
     }
-    cpu.regs.set_b(b.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
 
-/// RRC C
-/// Shifts register C by one bit to the right.
-pub fn op_cb09(cpu: &mut Cpu) {
-    let c = cpu.regs.c();
-    if c & 1 == 1 {
-        cpu.regs.set_flags(Flags::C);
-    }
-    cpu.regs.set_c(c.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+    cpu.regs.set_hl(sum);
+    cpu.regs.set_f(flags);
 }
 
-/// RRC D
-/// Shifts register D by one bit to the right.
-pub fn op_cb0a(cpu: &mut Cpu) {
-    let d = cpu.regs.d();
-    if d & 1 == 1 {
-        cpu.regs.set_flags(Flags::C);
-    }
-    cpu.regs.set_d(d.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
+/// Consumes a signed byte and returns it added to SP.
+/// Sets the flags as ADD SP, i8 and LD HL, SP+i8 do: H and C come from 
+/// the unsigned addition of the lower byte of SP and the offset.
+fn sp_plus_offset(cpu: &mut Cpu, memory: &mut Memory) -> u16 {
+    let offset = cpu.consume_byte(memory);
+    let sp = cpu.regs.sp();
 
-/// RRC E
-/// Shifts register E by one bit to the right.
-pub fn op_cb0b(cpu: &mut Cpu) {
-    let e = cpu.regs.e();
-    if e & 1 == 1 {
-        cpu.regs.set_flags(Flags::C);
+    let mut flags = 0;
+    if (sp & 0x0f) + (offset as u16 & 0x0f) > 0x0f {
+        flags |= Flags::H;
     }
-    cpu.regs.set_e(e.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
-
-/// RRC H
-/// Shifts register H by one bit to the right.
-pub fn op_op_cb0c(cpu: &mut Cpu) {
-    let h = cpu.regs.h();
-    if h & 1 == 1 {
-        cpu.regs.set_flags(Flags::C);
+    if (sp & 0xff) + offset as u16 > 0xff {
+        flags |= Flags::C;
     }
-    cpu.regs.set_h(h.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
-}
+    cpu.regs.set_f(flags);
 
-/// RRC L
-/// Shifts register L by one bit to the right.
-pub fn op_cb0d(cpu: &mut Cpu) {
-    let l = cpu.regs.l();
-    if l & 1 == 1 {
-        cpu.regs.set_flags(Flags::C);
-    }
-    cpu.regs.set_l(l.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+    // The castings and their order are important: the offset is sign extended.
+    sp.wrapping_add(offset as i8 as u16)
 }
 
-/// RRC (HL)
-/// Shifts the byte at memory location pointed to by register HL by one bit to the right.
-pub fn op_cb0e(cpu: &mut Cpu, memory: &mut Memory) {
-    let hl = cpu.regs.hl();
-    let byte = memory.read_byte(hl as usize);
-    if byte & (1 << 7) == 1 {
-        cpu.regs.set_flags(Flags::C);
-    }
-    memory.write_byte(hl as usize, byte.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+/// Adds the signed `offset` to the PC.
+fn jump_relative(cpu: &mut Cpu, offset: i8) {
+    cpu.pc = cpu.pc.wrapping_add(offset as u16);
 }
 
-/// RRC A
-/// Shifts register A by one bit to the right.
-pub fn op_cb0f(cpu: &mut Cpu) {
-    let a = cpu.regs.a();
-    if a & 1 == 1 {
-        cpu.regs.set_flags(Flags::C);
-    }
-    cpu.regs.set_a(a.rotate_right(1));
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+/// Pushes `word` onto the stack, upper byte first,
+/// after an internal M-cycle decrementing SP.
+fn push_word(cpu: &mut Cpu, memory: &mut Memory, word: u16) {
+    cpu.idle(memory);
+    let sp = cpu.regs.sp().wrapping_sub(1);
+    cpu.write(memory, sp, (word >> 8) as u8);
+    let sp = sp.wrapping_sub(1);
+    cpu.write(memory, sp, word as u8);
+    cpu.regs.set_sp(sp);
 }
 
-/// RL C
-pub fn op_cb11(cpu: &mut Cpu) {
-    let carry_in = match cpu.regs.check_flags(Flags::C) {
-        true => 1,
-        false => 0,
-    };
-    let carry_out = match cpu.regs.c() & (1 << 7) {
-        0x80 => Flags::C,
-           _ => 0,
-    };
-    let c_shifted = cpu.regs.c() << 1;
-
-    cpu.regs.set_c(c_shifted | carry_in);
-
-    let mut flags = carry_out;
-    if cpu.regs.c() == 0 {
-        flags |= Flags::Z;
-    }
-    cpu.regs.set_flags(flags);
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+/// Pops a word off the stack, lower byte first.
+fn pop_word(cpu: &mut Cpu, memory: &mut Memory) -> u16 {
+    let sp = cpu.regs.sp();
+    let lower = cpu.read(memory, sp) as u16;
+    let upper = (cpu.read(memory, sp.wrapping_add(1)) as u16) << 8;
+    cpu.regs.set_sp(sp.wrapping_add(2));
+    upper | lower
 }
 
-/// RL A
-pub fn op_cb17(cpu: &mut Cpu) {
-    let carry_in = match cpu.regs.check_flags(Flags::C) {
-        true => 1,
-        false => 0,
-    };
-    let carry_out = match cpu.regs.c() & (1 << 7) {
-        0x80 => Flags::C,
-           _ => 0,
-    };
-    let a_shifted = cpu.regs.a() << 1;
+/* PREFIX INSTRUCTIONS */
 
-    cpu.regs.set_a(a_shifted | carry_in);
+/// Handles the prefixed instructions.
+/// Their cycles include fetching the prefix.
+pub fn op_cb(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.ir = cpu.consume_byte(memory);
+    lookup::INSTRS_PREFIX[cpu.ir as usize](cpu, memory)
+}
 
-    let mut flags = carry_out;
-    if cpu.regs.a() == 0 {
+/// BIT `bit`, `value`.
+/// Sets the Z flag if `bit` is unset in `value`, resets N and sets H. C is not modified.
+fn bit(cpu: &mut Cpu, bit: u8, value: u8) {
+    let mut flags = (cpu.regs.f() & Flags::C) | Flags::H;
+    if value & (1 << bit) == 0 {
         flags |= Flags::Z;
     }
-    cpu.regs.set_flags(flags);
-    cpu.regs.reset_flags(Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+    cpu.regs.set_f(flags);
+}
+
+/// RLC/RRC/RL/RR/SLA/SRA/SWAP/SRL r
+/// The operation is selected by the y field and the operand by the z field.
+pub fn cb_shift(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let operand = Operand::decode(z(cpu.ir));
+    let value = operand.read(cpu, memory);
+    let (new_value, new_flags) = match y(cpu.ir) {
+        0 => alu::rlc(value),
+        1 => alu::rrc(value),
+        2 => alu::rl(value, cpu.regs.f()),
+        3 => alu::rr(value, cpu.regs.f()),
+        4 => alu::sla(value),
+        5 => alu::sra(value),
+        6 => alu::swap(value),
+        _ => alu::srl(value),
+    };
+    operand.write(cpu, memory, new_value);
+    cpu.regs.set_f(new_flags);
+    8 + 2 * operand.access_cycles()
 }
 
-// BIT 7, H.
-// If bit 7 in register H is unset (= 0) then set the Z flag.
-// Reset the N flag, set the H flag.
-pub fn op_cb7c(cpu: &mut Cpu) {
-    if cpu.regs.h() & (1 << 7) == 0 {
-        cpu.regs.set_flags(Flags::Z);
-    }
-    cpu.regs.reset_flags(Flags::N);
-    cpu.regs.set_flags(Flags::H);
-    cpu.div_ctrl += 4;
+/// BIT y, r
+pub fn cb_bit(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let operand = Operand::decode(z(cpu.ir));
+    let value = operand.read(cpu, memory);
+    bit(cpu, y(cpu.ir), value);
+    8 + operand.access_cycles()
 }
 
-/* OTHER INSTRUCTIONS */
-
-/// NOP
-pub fn op_00(cpu: &mut Cpu) {
-    cpu.pc += 1;
-    cpu.div_ctrl += 4;
+/// RES y, r
+pub fn cb_res(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let operand = Operand::decode(z(cpu.ir));
+    let value = operand.read(cpu, memory) & !(1 << y(cpu.ir));
+    operand.write(cpu, memory, value);
+    8 + 2 * operand.access_cycles()
 }
 
-/// INC B
-pub fn op_04(cpu: &mut Cpu) {
-    let new_value = cpu.regs.b().wrapping_add(1);
-    let mut flags = 0;
-    if new_value == 0 {
-        flags |= Flags::Z;
-    }
-    // Only if the register value's lowest 4 bits are set will it overflow 
-    // from bit 3 when adding 1.
-    if cpu.regs.b() & 0b1111 == 0b1111 {
-        // Bit 3 overflowed.
-        flags |= Flags::H;
-    }
-
-    cpu.regs.set_b(new_value);
-    cpu.regs.set_flags(flags);
-    cpu.regs.reset_flags(Flags::N);
-    cpu.div_ctrl += 4;
+/// SET y, r
+pub fn cb_set(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let operand = Operand::decode(z(cpu.ir));
+    let value = operand.read(cpu, memory) | (1 << y(cpu.ir));
+    operand.write(cpu, memory, value);
+    8 + 2 * operand.access_cycles()
 }
 
-/// DEC B
-pub fn op_05(cpu: &mut Cpu) {
-    let new_value = cpu.regs.b().wrapping_sub(1);
-    let mut new_flags = 0;
-    if new_value == 0 {
-        new_flags |= Flags::Z;
-    }
-    new_flags |= Flags::N;
-    // Only if the register value's lowest set bit is the 4th bit will it overflow 
-    // (backwards) from bit 4 when subtracting 1.
-    if cpu.regs.b() & 0b11111 == 0b10000 {
-        // Bit 4 overflowed (backwards).
-        new_flags |= Flags::H;
-    }
+/* OPERAND DECODED INSTRUCTIONS */
 
-    cpu.regs.set_b(new_value);
-    cpu.regs.set_flags(new_flags);
-    cpu.div_ctrl += 4;
+/// LD r, r
+/// Loads the operand in the z field into the operand in the y field.
+/// 0x76, which would be LD (HL), (HL), is HALT instead.
+pub fn ld_r_r(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let dst = Operand::decode(y(cpu.ir));
+    let src = Operand::decode(z(cpu.ir));
+    let value = src.read(cpu, memory);
+    dst.write(cpu, memory, value);
+    4 + src.access_cycles() + dst.access_cycles()
 }
 
-/// LD B, u8
-pub fn op_06(cpu: &mut Cpu, memory: &mut Memory) {
+/// LD r, u8
+pub fn ld_r_u8(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let dst = Operand::decode(y(cpu.ir));
     let byte = cpu.consume_byte(memory);
-    cpu.regs.set_b(byte);
-    cpu.div_ctrl += 8;
+    dst.write(cpu, memory, byte);
+    8 + dst.access_cycles()
 }
 
-/// INC C
-pub fn op_0c(cpu: &mut Cpu) {
-    let new_value = cpu.regs.c().wrapping_add(1);
-    let mut flags = 0;
-    if new_value == 0 {
-        flags |= Flags::Z;
-    }
-    // Only if the register value's lowest 4 bits are set will it overflow 
-    // from bit 3 when adding 1.
-    if cpu.regs.c() & 0b1111 == 0b1111 {
-        // Bit 3 overflowed.
-        flags |= Flags::H;
-    }
+/// INC r
+pub fn inc_r(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let operand = Operand::decode(y(cpu.ir));
+    let (new_value, new_flags) = alu::inc(operand.read(cpu, memory), cpu.regs.f());
+    operand.write(cpu, memory, new_value);
+    cpu.regs.set_f(new_flags);
+    4 + 2 * operand.access_cycles()
+}
 
-    cpu.regs.set_c(new_value);
-    cpu.regs.set_flags(flags);
-    cpu.regs.reset_flags(Flags::N);
-    cpu.div_ctrl += 4;
+/// DEC r
+pub fn dec_r(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let operand = Operand::decode(y(cpu.ir));
+    let (new_value, new_flags) = alu::dec(operand.read(cpu, memory), cpu.regs.f());
+    operand.write(cpu, memory, new_value);
+    cpu.regs.set_f(new_flags);
+    4 + 2 * operand.access_cycles()
 }
 
-/// DEC C
-pub fn op_0d(cpu: &mut Cpu) {
-    let new_value = cpu.regs.c().wrapping_sub(1);
-    let mut new_flags = 0;
-    if new_value == 0 {
-        new_flags |= Flags::Z;
-    }
-    new_flags |= Flags::N;
-    // Only if the register value's lowest set bit is the 4th bit will it overflow 
-    // (backwards) from bit 4 when subtracting 1.
-    if cpu.regs.c() & 0b11111 == 0b10000 {
-        // Bit 4 overflowed (backwards).
-        new_flags |= Flags::H;
-    }
+/// Applies the ALU operation selected by the y field of `opcode` to register A and `value`.
+fn alu_a(cpu: &mut Cpu, opcode: u8, value: u8) {
+    let a = cpu.regs.a();
+    let f = cpu.regs.f();
+    let (new_a, new_f) = match y(opcode) {
+        0 => alu::add(a, value),
+        1 => alu::adc(a, value, f),
+        2 => alu::sub(a, value),
+        3 => alu::sbc(a, value, f),
+        4 => alu::and(a, value),
+        5 => alu::xor(a, value),
+        6 => alu::or(a, value),
+        _ => (a, alu::cp(a, value)),
+    };
+    cpu.regs.set_a(new_a);
+    cpu.regs.set_f(new_f);
+}
 
-    cpu.regs.set_c(new_value);
-    cpu.regs.set_flags(new_flags);
-    cpu.div_ctrl += 4;
+/// ADD/ADC/SUB/SBC/AND/XOR/OR/CP A, r
+pub fn alu_a_r(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let operand = Operand::decode(z(cpu.ir));
+    let value = operand.read(cpu, memory);
+    alu_a(cpu, cpu.ir, value);
+    4 + operand.access_cycles()
 }
 
-/// LD C, u8
-pub fn op_0e(cpu: &mut Cpu, memory: &mut Memory) {
+/// ADD/ADC/SUB/SBC/AND/XOR/OR/CP A, u8
+pub fn alu_a_u8(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
     let byte = cpu.consume_byte(memory);
-    cpu.regs.set_c(byte);
-    cpu.div_ctrl += 8;
+    alu_a(cpu, cpu.ir, byte);
+    8
 }
 
-/// LD DE, u16
-pub fn op_11(cpu: &mut Cpu, memory: &mut Memory) {
-    let lower = cpu.consume_byte(memory) as u16;
-    let upper = (cpu.consume_byte(memory) as u16) << 8;
-    cpu.regs.set_de(upper | lower);
-    cpu.div_ctrl += 12;
+/// LD rp, u16
+pub fn ld_rp_u16(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let word = cpu.consume_word(memory);
+    cpu.regs.set_reg16(Reg16::from_rp(p(cpu.ir)), word);
+    12
 }
 
-/// INC DE.
+/// INC rp.
 /// No flags are modified in this instruction.
-pub fn op_13(cpu: &mut Cpu) {
-    let new_de = cpu.regs.de().wrapping_add(1);
-    cpu.regs.set_de(new_de);
-    cpu.div_ctrl += 8;
-}
-
-/// DEC D
-pub fn op_15(cpu: &mut Cpu) {
-    let new_value = cpu.regs.d().wrapping_sub(1);
-    let mut new_flags = 0;
-    if new_value == 0 {
-        new_flags |= Flags::Z;
-    }
-    new_flags |= Flags::N;
-    // Only if the register value's lowest set bit is the 4th bit will it overflow 
-    // (backwards) from bit 4 when subtracting 1.
-    if cpu.regs.d() & 0b11111 == 0b10000 {
-        // Bit 4 overflowed (backwards).
-        new_flags |= Flags::H;
-    }
-
-    cpu.regs.set_d(new_value);
-    cpu.regs.set_flags(new_flags);
-    cpu.div_ctrl += 4;
+pub fn inc_rp(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let reg = Reg16::from_rp(p(cpu.ir));
+    cpu.regs.set_reg16(reg, cpu.regs.reg16(reg).wrapping_add(1));
+    8
 }
 
-/// LD D, u8
-pub fn op_16(cpu: &mut Cpu, memory: &mut Memory) {
-    let byte = cpu.consume_byte(memory);
-    cpu.regs.set_d(byte);
-    cpu.div_ctrl += 8;
+/// DEC rp.
+/// No flags are modified in this instruction.
+pub fn dec_rp(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let reg = Reg16::from_rp(p(cpu.ir));
+    cpu.regs.set_reg16(reg, cpu.regs.reg16(reg).wrapping_sub(1));
+    8
 }
 
-/// RLA
-pub fn op_17(cpu: &mut Cpu) {
-    let carry_in = match cpu.regs.check_flags(Flags::C) {
-        true => 1,
-        false => 0,
-    };
-    let carry_out = match cpu.regs.a() & (1 << 7) {
-        0x8 => Flags::C,
-           _ => 0,
-    };
-    let a_shifted = cpu.regs.a() << 1;
+/// ADD HL, rp
+pub fn add_hl_rp(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let value = cpu.regs.reg16(Reg16::from_rp(p(cpu.ir)));
+    add_hl(cpu, value);
+    8
+}
 
-    cpu.regs.set_a(a_shifted | carry_in);
+/// PUSH rp2
+pub fn push_rp2(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let word = cpu.regs.reg16(Reg16::from_rp2(p(cpu.ir)));
+    push_word(cpu, memory, word);
+    16
+}
 
-    cpu.regs.set_flags(carry_out);
-    cpu.regs.reset_flags(Flags::Z | Flags::N | Flags::H);
-    cpu.div_ctrl += 4;
+/// POP rp2
+pub fn pop_rp2(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let reg = Reg16::from_rp2(p(cpu.ir));
+    let mut word = pop_word(cpu, memory);
+    if reg == Reg16::AF {
+        // The lower nibble of F always reads back as zero.
+        word &= 0xfff0;
+    }
+    cpu.regs.set_reg16(reg, word);
+    12
 }
 
-/// JR i8
-pub fn op_18(cpu: &mut Cpu, memory: &mut Memory) {
-    // The castings and their order in this function are important 
-    // and should not be changed. Otherwise the values won't be translated correctly.
+/// JR cc, i8.
+/// Jump relatively if the condition holds.
+pub fn jr_cc(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
     let offset = cpu.consume_byte(memory) as i8;
-    cpu.pc = (cpu.pc as i16).wrapping_add(offset as i16) as usize;
-    cpu.div_ctrl += 12;
+    if condition(cpu, y(cpu.ir)) {
+        jump_relative(cpu, offset);
+        12
+    } else {
+        8
+    }
 }
 
-/// LD A, (DE)
-pub fn op_1a(cpu: &mut Cpu, memory: &mut Memory) {
-    let addr = cpu.regs.de() as usize;
-    cpu.regs.set_a(memory.read_byte(addr));
-    cpu.div_ctrl += 8;
-}
-
-/// DEC E
-pub fn op_1d(cpu: &mut Cpu) {
-    let new_value = cpu.regs.e().wrapping_sub(1);
-    let mut new_flags = 0;
-    if new_value == 0 {
-        new_flags |= Flags::Z;
-    }
-    new_flags |= Flags::N;
-    // Only if the register value's lowest set bit is the 4th bit will it overflow 
-    // (backwards) from bit 4 when subtracting 1.
-    if cpu.regs.e() & 0b11111 == 0b10000 {
-        // Bit 4 overflowed (backwards).
-        new_flags |= Flags::H;
+/// JP cc, u16.
+/// Jump if the condition holds.
+pub fn jp_cc(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.consume_word(memory);
+    if condition(cpu, y(cpu.ir)) {
+        cpu.pc = addr;
+        16
+    } else {
+        12
     }
-
-    cpu.regs.set_e(new_value);
-    cpu.regs.set_flags(new_flags);
-    cpu.div_ctrl += 4;
 }
 
-/// LD E, u8
-pub fn op_1e(cpu: &mut Cpu, memory: &mut Memory) {
-    let byte = cpu.consume_byte(memory);
-    cpu.regs.set_e(byte);
-    cpu.div_ctrl += 8;
+/// CALL cc, u16.
+/// Call if the condition holds.
+pub fn call_cc(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.consume_word(memory);
+    if condition(cpu, y(cpu.ir)) {
+        push_word(cpu, memory, cpu.pc);
+        cpu.pc = addr;
+        24
+    } else {
+        12
+    }
 }
 
-/// JR NZ, i8. 
-/// Jump relatively if the Z flag is not set.
-pub fn op_20(cpu: &mut Cpu, memory: &mut Memory) {
-    // The castings and their order in this function are important 
-    // and should not be changed. Otherwise the values won't be translated correctly.
-    let offset = cpu.consume_byte(memory) as i8;
-    if !cpu.regs.check_flags(Flags::Z) {
-        cpu.pc = (cpu.pc as i16).wrapping_add(offset as i16) as usize;
-        cpu.div_ctrl += 12;
+/// RET cc.
+/// Return if the condition holds, which takes an internal M-cycle to check.
+pub fn ret_cc(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.idle(memory);
+    if condition(cpu, y(cpu.ir)) {
+        let addr = pop_word(cpu, memory);
+        cpu.idle(memory);
+        cpu.pc = addr;
+        20
     } else {
-        cpu.div_ctrl += 8;
+        8
     }
 }
 
-/// LD HL, u16
-pub fn op_21(cpu: &mut Cpu, memory: &mut Memory) {
-    let lower = cpu.consume_byte(memory) as u16;
-    let upper = (cpu.consume_byte(memory) as u16) << 8;
-    cpu.regs.set_hl(upper | lower);
-    cpu.div_ctrl += 12;
+/// RST n
+/// Calls the fixed address y * 8.
+pub fn rst(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    push_word(cpu, memory, cpu.pc);
+    cpu.pc = (y(cpu.ir) as u16) * 8;
+    16
 }
 
-/// LD (HL+), A
-pub fn op_22(cpu: &mut Cpu, memory: &mut Memory) {
-    memory.write_byte(cpu.regs.hl() as usize, cpu.regs.a());
-    let new_hl = cpu.regs.hl().wrapping_add(1);
-    cpu.regs.set_hl(new_hl);
-    cpu.div_ctrl += 8;
+/* OTHER INSTRUCTIONS */
+
+/// Any of the opcodes that have no instruction on the SM83.
+/// Locks the CPU up until it is reset.
+pub fn op_illegal(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    cpu.state = RunState::Locked;
+    4
 }
 
-/// INC HL.
-/// No flags are modified in this instruction.
-pub fn op_23(cpu: &mut Cpu) {
-    let new_hl = cpu.regs.hl().wrapping_add(1);
-    cpu.regs.set_hl(new_hl);
-    cpu.div_ctrl += 8;
+/// NOP
+pub fn op_00(_cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    4
 }
 
-/// INC H
-pub fn op_24(cpu: &mut Cpu) {
-    let new_value = cpu.regs.h().wrapping_add(1);
-    let mut flags = 0;
-    if new_value == 0 {
-        flags |= Flags::Z;
-    }
-    // Only if the register value's lowest 4 bits are set will it overflow 
-    // from bit 3 when adding 1.
-    if cpu.regs.h() & 0b1111 == 0b1111 {
-        // Bit 3 overflowed.
-        flags |= Flags::H;
-    }
+/// LD (BC), A
+pub fn op_02(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.write(memory, cpu.regs.bc(), cpu.regs.a());
+    8
+}
 
-    cpu.regs.set_h(new_value);
-    cpu.regs.set_flags(flags);
-    cpu.regs.reset_flags(Flags::N);
-    cpu.div_ctrl += 4;
+/// RLCA
+/// Rotates register A one bit to the left. Bit 7 goes into both bit 0 and the C flag.
+pub fn op_07(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let (new_a, new_f) = alu::rlc(cpu.regs.a());
+    cpu.regs.set_a(new_a);
+    // Unlike RLC A, the Z flag is always reset.
+    cpu.regs.set_f(new_f & Flags::C);
+    4
 }
 
-/// JR Z, i8. 
-/// Jump relatively if the Z flag is set.
-pub fn op_28(cpu: &mut Cpu, memory: &mut Memory) {
-    // The castings and their order in this function are important 
-    // and should not be changed. Otherwise the values won't be translated correctly.
-    let offset = cpu.consume_byte(memory) as i8;
-    if cpu.regs.check_flags(Flags::Z) {
-        cpu.pc = (cpu.pc as i16).wrapping_add(offset as i16) as usize;
-        cpu.div_ctrl += 12;
-    } else {
-        cpu.div_ctrl += 8;
-    }
+/// LD (u16), SP
+pub fn op_08(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.consume_word(memory);
+    let sp = cpu.regs.sp();
+    cpu.write(memory, addr, sp as u8);
+    cpu.write(memory, addr.wrapping_add(1), (sp >> 8) as u8);
+    20
 }
 
-/// LD SP, u16
-/// REMEMBER: the GameBoy is little endian, meaning 
-/// the first byte is least significant.
-pub fn op_31(cpu: &mut Cpu, memory: &mut Memory) {
-    let lower = cpu.consume_byte(memory) as u16;
-    let upper = (cpu.consume_byte(memory) as u16) << 8;
-    cpu.sp = (upper | lower) as usize;
-    cpu.div_ctrl += 12;
+/// LD A, (BC)
+pub fn op_0a(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.regs.bc();
+    let value = cpu.read(memory, addr);
+    cpu.regs.set_a(value);
+    8
 }
 
-/// LD (HL-), A
-pub fn op_32(cpu: &mut Cpu, memory: &mut Memory) {
-    // load A into (HL)
-    memory.write_byte(cpu.regs.hl() as usize, cpu.regs.a());
-    // decrement HL
-    cpu.regs.set_hl(cpu.regs.hl().wrapping_sub(1));
-    cpu.div_ctrl += 8;
+/// RRCA
+/// Rotates register A one bit to the right. Bit 0 goes into both bit 7 and the C flag.
+pub fn op_0f(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let (new_a, new_f) = alu::rrc(cpu.regs.a());
+    cpu.regs.set_a(new_a);
+    // Unlike RRC A, the Z flag is always reset.
+    cpu.regs.set_f(new_f & Flags::C);
+    4
 }
 
-/// DEC A
-pub fn op_3d(cpu: &mut Cpu) {
-    let new_value = cpu.regs.a().wrapping_sub(1);
-    let mut new_flags = 0;
-    if new_value == 0 {
-        new_flags |= Flags::Z;
-    }
-    new_flags |= Flags::N;
-    // Only if the register value's lowest set bit is the 4th bit will it overflow 
-    // (backwards) from bit 4 when subtracting 1.
-    if cpu.regs.a() & 0b11111 == 0b10000 {
-        // Bit 4 overflowed (backwards).
-        new_flags |= Flags::H;
+/// STOP
+/// Resets DIV and stops the CPU until a joypad button is pressed.
+/// If the CGB speed switch is armed, switches the speed instead of stopping,
+/// without emulating the pause this takes. The byte following the opcode is
+/// read and skipped, so this takes 8 T-cycles. Both timings tick them before DIV
+/// is reset, as the clock stops along with the CPU.
+pub fn op_10(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.consume_byte(memory);
+    cpu.catch_up(memory, 8);
+    memory.write_byte(DIV, 0);
+    if !memory.switch_speed() {
+        cpu.state = RunState::Stopped;
     }
-
-    cpu.regs.set_a(new_value);
-    cpu.regs.set_flags(new_flags);
-    cpu.div_ctrl += 4;
+    8
 }
 
-/// LD A, u8
-pub fn op_3e(cpu: &mut Cpu, memory: &mut Memory) {
-    let byte = cpu.consume_byte(memory);
-    cpu.regs.set_a(byte);
-    cpu.div_ctrl += 8;
+/// LD (DE), A
+pub fn op_12(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.write(memory, cpu.regs.de(), cpu.regs.a());
+    8
 }
 
-/// LD C, A
-pub fn op_4f(cpu: &mut Cpu) {
-    let a = cpu.regs.a();
-    cpu.regs.set_c(a);
-    cpu.div_ctrl += 4;
+/// RLA
+/// Rotates register A one bit to the left through the C flag.
+pub fn op_17(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let (new_a, new_f) = alu::rl(cpu.regs.a(), cpu.regs.f());
+    cpu.regs.set_a(new_a);
+    // Unlike RL A, the Z flag is always reset.
+    cpu.regs.set_f(new_f & Flags::C);
+    4
 }
 
-/// LD D, A
-pub fn op_57(cpu: &mut Cpu) {
-    let a = cpu.regs.a();
-    cpu.regs.set_d(a);
-    cpu.div_ctrl += 4;
+/// JR i8
+pub fn op_18(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let offset = cpu.consume_byte(memory) as i8;
+    jump_relative(cpu, offset);
+    12
 }
 
-/// LD H, A
-pub fn op_67(cpu: &mut Cpu) {
-    let a = cpu.regs.a();
-    cpu.regs.set_h(a);
-    cpu.div_ctrl += 4;
+/// LD A, (DE)
+pub fn op_1a(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.regs.de();
+    let value = cpu.read(memory, addr);
+    cpu.regs.set_a(value);
+    8
 }
 
-/// LD (HL), A
-pub fn op_77(cpu: &mut Cpu, memory: &mut Memory) {
-    memory.write_byte(cpu.regs.hl() as usize, cpu.regs.a());
-    cpu.div_ctrl += 8;
+/// RRA
+/// Rotates register A one bit to the right through the C flag.
+pub fn op_1f(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let (new_a, new_f) = alu::rr(cpu.regs.a(), cpu.regs.f());
+    cpu.regs.set_a(new_a);
+    // Unlike RR A, the Z flag is always reset.
+    cpu.regs.set_f(new_f & Flags::C);
+    4
 }
 
-/// LD A, E
-pub fn op_7b(cpu: &mut Cpu) {
-    let e = cpu.regs.e();
-    cpu.regs.set_a(e);
-    cpu.div_ctrl += 4;
+/// LD (HL+), A
+pub fn op_22(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.write(memory, cpu.regs.hl(), cpu.regs.a());
+    let new_hl = cpu.regs.hl().wrapping_add(1);
+    cpu.regs.set_hl(new_hl);
+    8
 }
 
-/// LD A, H
-pub fn op_7c(cpu: &mut Cpu) {
-    let h = cpu.regs.h();
-    cpu.regs.set_a(h);
-    cpu.div_ctrl += 4;
+/// DAA
+/// Adjusts register A to hold the binary coded decimal result of the last addition or subtraction.
+pub fn op_27(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let (new_a, new_f) = alu::daa(cpu.regs.a(), cpu.regs.f());
+    cpu.regs.set_a(new_a);
+    cpu.regs.set_f(new_f);
+    4
 }
 
-/// ADD A, B
-pub fn op_80(cpu: &mut Cpu) {
-    let a = cpu.regs.a();
-    let b = cpu.regs.b();
-    let sum = a.wrapping_add(b);
-
-    if sum == 0 {
-        cpu.regs.set_flags(Flags::Z);
-    }
-    if a & (1 << 3) == 1 && b & (1 << 3) == 1 { // Check for half-carry
-        cpu.regs.set_flags(Flags::H);
-    }
-    if a & (1 << 7) == 1 && b & (1 << 7) == 1 { // Check for carry
-        cpu.regs.set_flags(Flags::C);
-    }
+/// LD A, (HL+)
+pub fn op_2a(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let value = cpu.read(memory, cpu.regs.hl());
+    cpu.regs.set_a(value);
+    let new_hl = cpu.regs.hl().wrapping_add(1);
+    cpu.regs.set_hl(new_hl);
+    8
+}
 
-    cpu.regs.reset_flags(Flags::N);
-    cpu.regs.set_a(sum);
-    cpu.div_ctrl += 4;
+/// CPL
+/// Flips all bits in register A.
+pub fn op_2f(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    cpu.regs.set_a(!cpu.regs.a());
+    cpu.regs.set_flags(Flags::N | Flags::H);
+    4
 }
 
-/// SUB A, B
-pub fn op_90(cpu: &mut Cpu) {
-    let a = cpu.regs.a();
-    let b = cpu.regs.b();
-    let diff = a.wrapping_sub(b);
+/// LD (HL-), A
+pub fn op_32(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    // load A into (HL)
+    cpu.write(memory, cpu.regs.hl(), cpu.regs.a());
+    // decrement HL
+    cpu.regs.set_hl(cpu.regs.hl().wrapping_sub(1));
+    8
+}
 
-    let mut flags = Flags::N;
-    if diff == 0 {
-        flags |= Flags::Z;
-    }
-    if a & (1 << 4) == 1 && b & (1 << 4) == 1 { 
-        flags |= Flags::H;
-    }
-    if b > a {
-        flags |= Flags::C;
-    }
+/// SCF
+/// Sets the C flag.
+pub fn op_37(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    cpu.regs.reset_flags(Flags::N | Flags::H);
+    cpu.regs.set_flags(Flags::C);
+    4
+}
 
-    cpu.regs.set_a(diff);
-    cpu.div_ctrl += 4;
+/// LD A, (HL-)
+pub fn op_3a(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let value = cpu.read(memory, cpu.regs.hl());
+    cpu.regs.set_a(value);
+    let new_hl = cpu.regs.hl().wrapping_sub(1);
+    cpu.regs.set_hl(new_hl);
+    8
 }
 
-/// XOR A, A
-pub fn op_af(cpu: &mut Cpu) {
-    cpu.regs.set_a(cpu.regs.a() ^ cpu.regs.a());
-    cpu.regs.set_flags(Flags::Z); // No need to check if the A is now zero.
-    cpu.div_ctrl += 4;
+/// CCF
+/// Flips the C flag.
+pub fn op_3f(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let f = cpu.regs.f();
+    cpu.regs.set_f((f & Flags::Z) | ((f & Flags::C) ^ Flags::C));
+    4
 }
 
-/// POP BC
-pub fn op_c1(cpu: &mut Cpu, memory: &mut Memory) {
-    let lower = memory.read_byte(cpu.sp);
-    cpu.sp += 1;
-    let upper = memory.read_byte(cpu.sp);
-    cpu.sp += 1;
-    cpu.regs.set_c(lower);
-    cpu.regs.set_b(upper);
-    cpu.div_ctrl += 12;
+/// HALT
+/// Halts the CPU until an interrupt is pending.
+/// If one already is while the IME is unset, the CPU doesn't halt and instead
+/// fails to increment the PC when fetching the next opcode (the HALT bug).
+pub fn op_76(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    if !cpu.ime && memory.interrupts().pending().is_some() {
+        cpu.halt_bug = true;
+    } else {
+        cpu.state = RunState::Halted;
+    }
+    4
 }
 
-/// PUSH BC
-pub fn op_c5(cpu: &mut Cpu, memory: &mut Memory) {
-    cpu.sp -= 1;
-    memory.write_byte(cpu.sp, cpu.regs.b());
-    cpu.sp -= 1;
-    memory.write_byte(cpu.sp, cpu.regs.c());
-    cpu.div_ctrl += 16;
+/// JP u16
+pub fn op_c3(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.pc = cpu.consume_word(memory);
+    16
 }
 
 /// RET
-pub fn op_c9(cpu: &mut Cpu, memory: &mut Memory) {
-    let lower = memory.read_byte(cpu.sp) as usize;
-    cpu.sp += 1;
-    let upper = (memory.read_byte(cpu.sp) as usize) << 8;
-    cpu.sp += 1;
-    cpu.pc = upper | lower;
-    cpu.div_ctrl += 16;
+pub fn op_c9(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.pc = pop_word(cpu, memory);
+    16
 }
 
 /// CALL u16
-pub fn op_cd(cpu: &mut Cpu, memory: &mut Memory) {
-    // Grab the new PC value.
-    let lower = cpu.consume_byte(memory) as u16;
-    let upper = (cpu.consume_byte(memory) as u16) << 8;
-
-    // Grab the current PC value.
-    let lower_pc = cpu.pc as u8;
-    let upper_pc = (cpu.pc >> 8) as u8;
-
-    // Push the current PC value to the stack.
-    cpu.sp -= 1;
-    memory.write_byte(cpu.sp, upper_pc);
-    cpu.sp -= 1;
-    memory.write_byte(cpu.sp, lower_pc);
-
-    // Perform the jump to the new address.
-    cpu.pc = (upper | lower) as usize;
+pub fn op_cd(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.consume_word(memory);
+    push_word(cpu, memory, cpu.pc);
+    cpu.pc = addr;
+    24
+}
 
-    cpu.div_ctrl += 24;
+/// RETI
+/// Returns and enables the Interrupt Master Enable flag (IME), without EI's delay.
+pub fn op_d9(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.pc = pop_word(cpu, memory);
+    cpu.ime = true;
+    16
 }
 
 /// LD (FF00+u8), A
-pub fn op_e0(cpu: &mut Cpu, memory: &mut Memory) {
+pub fn op_e0(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
     let offset = cpu.consume_byte(memory);
-    memory.write_byte(0xff00 + offset as usize, cpu.regs.a());
-    cpu.div_ctrl += 12;
+    cpu.write(memory, 0xff00 | offset as u16, cpu.regs.a());
+    12
 }
 
 /// LD (FF00+C), A
-pub fn op_e2(cpu: &mut Cpu, memory: &mut Memory) {
-    // 0xff00 + C will never overflow, so no need to wrap here.
-    memory.write_byte(0xff00 + cpu.regs.c() as usize, cpu.regs.a());
-    cpu.div_ctrl += 8;
+pub fn op_e2(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.write(memory, 0xff00 | cpu.regs.c() as u16, cpu.regs.a());
+    8
+}
+
+/// ADD SP, i8
+pub fn op_e8(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let word = sp_plus_offset(cpu, memory);
+    cpu.regs.set_sp(word);
+    16
+}
+
+/// JP HL
+/// Jumps to the address in register HL.
+pub fn op_e9(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    cpu.pc = cpu.regs.hl();
+    4
 }
 
 /// LD (u16), A
-pub fn op_ea(cpu: &mut Cpu, memory: &mut Memory) {
-    let lower = cpu.consume_byte(memory);
-    let upper = cpu.consume_byte(memory);
-    memory.write_byte((upper | lower) as usize, cpu.regs.a());
-    cpu.div_ctrl += 16;
+pub fn op_ea(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.consume_word(memory);
+    cpu.write(memory, addr, cpu.regs.a());
+    16
 }
 
 /// LD A, (FF00+u8)
-pub fn op_f0(cpu: &mut Cpu, memory: &mut Memory) {
+pub fn op_f0(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
     let byte = cpu.consume_byte(memory);
-    // 0xff00 + u8 will never overflow, so no need to wrap here.
-    cpu.regs.set_a(memory.read_byte(0xff00 + byte as usize));
-    cpu.div_ctrl += 12;
+    let value = cpu.read(memory, 0xff00 | byte as u16);
+    cpu.regs.set_a(value);
+    12
+}
+
+/// LD A, (FF00+C)
+pub fn op_f2(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let value = cpu.read(memory, 0xff00 | cpu.regs.c() as u16);
+    cpu.regs.set_a(value);
+    8
 }
 
 /// DI
-/// Disables the Interrupt Master Enable flag (IME).
-pub fn op_f3(cpu: &mut Cpu) {
+/// Disables the Interrupt Master Enable flag (IME), cancelling a pending EI.
+pub fn op_f3(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
     cpu.ime = false;
-    cpu.div_ctrl += 4;
+    cpu.ime_scheduled = false;
+    4
 }
 
-/// CP A, u8
-pub fn op_fe(cpu: &mut Cpu, memory: &mut Memory) {
-    let a = cpu.regs.a();
-    let byte = cpu.consume_byte(memory);
-    let diff = a.wrapping_sub(byte);
+/// LD HL, SP+i8
+pub fn op_f8(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let word = sp_plus_offset(cpu, memory);
+    cpu.regs.set_hl(word);
+    12
+}
 
-    let mut flags = Flags::N;
-    if diff == 0 {
-        flags |= Flags::Z;
-    }
-    if a & (1 << 4) == 1 && byte & (1 << 4) == 1 { 
-        flags |= Flags::H;
-    }
-    if byte > a {
-        flags |= Flags::C;
-    }
+/// LD SP, HL
+pub fn op_f9(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    let hl = cpu.regs.hl();
+    cpu.regs.set_sp(hl);
+    8
+}
 
-    cpu.div_ctrl += 8;
+/// LD A, (u16)
+pub fn op_fa(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    let addr = cpu.consume_word(memory);
+    let value = cpu.read(memory, addr);
+    cpu.regs.set_a(value);
+    16
 }
 
+/// EI
+/// Enables the Interrupt Master Enable flag (IME) after the following instruction.
+pub fn op_fb(cpu: &mut Cpu, _memory: &mut Memory) -> u8 {
+    cpu.ime_scheduled = true;
+    4
+}
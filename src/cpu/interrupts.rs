@@ -1,32 +1,224 @@
+//! Servicing the interrupts requested from the `InterruptController`.
+
 use crate::cpu::Cpu;
 use crate::memory::Memory;
 
-pub fn vblank_interrupt_handler(cpu: &mut Cpu, memory: &mut Memory) {
-    // Reset the IME
-    cpu.ime = false; 
-    
-    // Reset the corresponding bit in the IF register.
-    let interrupt_requests = memory.read_byte(0xff0f); 
-    memory.write_byte(0xff0f, interrupt_requests & (1 ^ 0xff));
+/// Dispatches the highest priority pending interrupt.
+/// Resets the IME, pushes the PC and jumps to the interrupt's handler,
+/// acknowledging it by resetting its bit in IF. Returns the 20 T-cycles this takes.
+///
+/// Which interrupt is serviced is only decided after the upper byte of the PC
+/// has been pushed. If that push overwrote IE (SP was 0x0000) and no enabled
+/// interrupt is left, the dispatch is cancelled: IF is left untouched and
+/// execution continues at 0x0000.
+///
+/// If the HALT bug is pending (EI followed by HALT), the pushed PC is the HALT's
+/// own address, so it is executed again once the handler returns.
+pub fn dispatch(cpu: &mut Cpu, memory: &mut Memory) -> u8 {
+    cpu.ime = false;
 
-    // TODO: the rest of this thing...
+    let mut pc = cpu.pc;
+    if cpu.halt_bug {
+        cpu.halt_bug = false;
+        pc = pc.wrapping_sub(1);
+    }
+    // Two internal M-cycles, then the PC is pushed.
+    cpu.idle(memory);
+    cpu.idle(memory);
 
-    unimplemented!("V-Blank interrupt handler called.");
-}
+    let sp = cpu.regs.sp().wrapping_sub(1);
+    cpu.write(memory, sp, (pc >> 8) as u8);
 
-pub fn lcd_stat_interrupt_handler(cpu: &mut Cpu, memory: &mut Memory) { 
-    unimplemented!("LCD STAT interrupt handler called.");
-}
+    let interrupt = memory.interrupts().pending();
 
-pub fn timer_interrupt_handler(cpu: &mut Cpu, memory: &mut Memory) { 
-    unimplemented!("Timer interrupt handler called.");
-}
+    let sp = sp.wrapping_sub(1);
+    cpu.write(memory, sp, pc as u8);
+    cpu.regs.set_sp(sp);
 
-pub fn serial_interrupt_handler(cpu: &mut Cpu, memory: &mut Memory) { 
-    unimplemented!("Serial interrupt handler called.");
-}
+    match interrupt {
+        Some(interrupt) => {
+            memory.interrupts_mut().acknowledge(interrupt);
+            cpu.pc = interrupt.vector();
+        }
+        None => cpu.pc = 0x0000,
+    }
 
-pub fn joypad_interrupt_handler(cpu: &mut Cpu, memory: &mut Memory) { 
-    unimplemented!("Joypad interrupt handler called.");
+    20
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+    use crate::interrupts::{ Interrupt, IE, IF };
+
+    /// Returns a CPU about to execute at `pc` with the stack at `sp`,
+    /// and memory with the given IE and IF.
+    fn setup(pc: u16, sp: u16, ie: u8, if_: u8) -> (Cpu, Memory) {
+        let mut cpu = Cpu::new();
+        cpu.pc = pc;
+        cpu.regs.set_sp(sp);
+        cpu.ime = true;
+
+        let mut memory = Memory::new();
+        memory.write_byte(IE, ie);
+        memory.write_byte(IF, if_);
+        (cpu, memory)
+    }
+
+    /// Assembles `program` into memory, starting at `addr`.
+    fn load(memory: &mut Memory, addr: u16, program: &str) {
+        asm::load(memory, addr, program).unwrap();
+    }
+
+    #[test]
+    fn dispatches_the_highest_priority_interrupt() {
+        let (mut cpu, mut memory) = setup(0x1234, 0xfffe, 0b10100, 0b11100);
+
+        let info = cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.pc, 0x50); // Timer
+        assert!(!cpu.ime);
+        assert_eq!(memory.read_byte(IF), 0b1111_1000);
+        assert_eq!(cpu.regs.sp(), 0xfffc);
+        assert_eq!(memory.read_byte(0xfffd), 0x12);
+        assert_eq!(memory.read_byte(0xfffc), 0x34);
+        assert_eq!(info.cycles, 20);
+    }
+
+    #[test]
+    fn every_interrupt_has_its_own_handler() {
+        for interrupt in Interrupt::ALL {
+            let (mut cpu, mut memory) = setup(0x0150, 0xdfff, 0x1f, interrupt.mask());
+
+            cpu.step(&mut memory).unwrap();
+
+            assert_eq!(cpu.pc, interrupt.vector());
+            assert_eq!(memory.read_byte(IF), 0xe0);
+        }
+    }
+
+    #[test]
+    fn nothing_is_dispatched_without_the_ime() {
+        let (mut cpu, mut memory) = setup(0x0150, 0xfffe, 0x01, 0x01);
+        cpu.ime = false;
+
+        cpu.step(&mut memory).unwrap();
+
+        // The NOP at 0x0150 was executed instead.
+        assert_eq!(memory.read_byte(IF), 0xe1);
+        assert_eq!(cpu.regs.sp(), 0xfffe);
+    }
+
+    #[test]
+    fn pushing_the_pc_over_ie_cancels_the_dispatch() {
+        // The upper byte of the PC (0x02) lands in IE, which disables V-Blank.
+        let (mut cpu, mut memory) = setup(0x0200, 0x0000, 0x01, 0x01);
+
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.pc, 0x0000);
+        assert!(!cpu.ime);
+        assert_eq!(memory.read_byte(IE), 0x02);
+        assert_eq!(memory.read_byte(IF), 0xe1);
+        assert_eq!(cpu.regs.sp(), 0xfffe);
+        assert_eq!(memory.read_byte(0xfffe), 0x00);
+    }
+
+    #[test]
+    fn pushing_the_pc_over_ie_can_change_the_interrupt() {
+        // V-Blank was the higher priority, but the upper byte of the PC (0x02)
+        // leaves only LCD STAT enabled.
+        let (mut cpu, mut memory) = setup(0x0200, 0x0000, 0x01, 0x03);
+
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.pc, 0x48);
+        assert_eq!(memory.read_byte(IF), 0xe1);
+    }
+
+    #[test]
+    fn pushing_only_the_lower_byte_over_ie_does_not_cancel() {
+        // The lower byte of the PC (0x00) lands in IE after the interrupt was chosen.
+        let (mut cpu, mut memory) = setup(0x0200, 0x0001, 0x01, 0x01);
+
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.pc, 0x40);
+        assert_eq!(memory.read_byte(IE), 0x00);
+        assert_eq!(memory.read_byte(IF), 0xe0);
+    }
+
+    #[test]
+    fn ei_enables_the_ime_after_the_following_instruction() {
+        let (mut cpu, mut memory) = setup(0x0150, 0xfffe, 0x01, 0x01);
+        cpu.ime = false;
+        load(&mut memory, 0x0150, "ei\n inc b\n inc b");
+
+        cpu.step(&mut memory).unwrap();
+        assert!(!cpu.ime);
+        assert_eq!(cpu.pc, 0x0151);
+
+        cpu.step(&mut memory).unwrap();
+        assert!(cpu.ime);
+        assert_eq!(cpu.pc, 0x0152);
+
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.pc, 0x40);
+        assert_eq!(memory.read_byte(0xfffd), 0x01);
+        assert_eq!(memory.read_byte(0xfffc), 0x52);
+    }
+
+    #[test]
+    fn ei_followed_by_di_never_enables_the_ime() {
+        let (mut cpu, mut memory) = setup(0x0150, 0xfffe, 0x01, 0x01);
+        cpu.ime = false;
+        load(&mut memory, 0x0150, "ei\n di\n inc b");
+
+        for _ in 0..3 {
+            cpu.step(&mut memory).unwrap();
+            assert!(!cpu.ime);
+        }
+
+        assert_eq!(cpu.pc, 0x0153);
+        assert_eq!(memory.read_byte(IF), 0xe1);
+    }
+
+    #[test]
+    fn ei_followed_by_halt_services_the_waking_interrupt() {
+        let (mut cpu, mut memory) = setup(0x0150, 0xfffe, 0x01, 0x00);
+        cpu.ime = false;
+        load(&mut memory, 0x0150, "ei\n halt");
+
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+        assert!(cpu.ime);
+
+        // Nothing is pending, so the CPU stays halted.
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.pc, 0x0152);
+
+        memory.write_byte(IF, 0x01);
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.pc, 0x40);
+        assert!(!cpu.ime);
+        assert_eq!(memory.read_byte(0xfffd), 0x01);
+        assert_eq!(memory.read_byte(0xfffc), 0x52);
+    }
+
+    #[test]
+    fn reti_enables_the_ime_immediately() {
+        let (mut cpu, mut memory) = setup(0x0040, 0xfffc, 0x01, 0x01);
+        cpu.ime = false;
+        load(&mut memory, 0x0040, "reti");
+        load(&mut memory, 0xfffc, "db $52, $01");
+
+        cpu.step(&mut memory).unwrap();
+        assert!(cpu.ime);
+        assert_eq!(cpu.pc, 0x0152);
+
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.pc, 0x40);
+    }
+}
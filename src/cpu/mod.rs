@@ -1,23 +1,162 @@
+use std::collections::HashSet;
+use std::fmt;
+
 use crate::memory::Memory;
+use crate::model::Model;
 
-mod registers;
+pub mod registers;
 use registers::*;
 
-mod instructions;
-use instructions::*;
+pub mod alu;
+
+pub mod instructions;
+
+pub(crate) mod interrupts;
+
+pub mod trace;
+use trace::Tracer;
+
+/// The opcode of LD B, B, which test ROMs such as Mooneye's use as a breakpoint.
+const LD_B_B: u8 = 0x40;
+
+/// The dots in a video frame: 154 lines of 456 dots.
+const DOTS_PER_FRAME: u64 = 154 * 456;
+
+/// Called with the state of the CPU after every `LD B, B`.
+pub type DebugHook = Box<dyn FnMut(&CpuState)>;
+
+/// What the CPU is doing between instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    /// Fetching and executing instructions.
+    Running,
+    /// Entered by HALT, until an interrupt is pending (even with the IME unset).
+    Halted,
+    /// Entered by STOP, until a joypad button is pressed.
+    Stopped,
+    /// Entered by executing an illegal opcode, the CPU never leaves it.
+    Locked,
+}
+
+/// When the rest of the system is advanced by the cycles the CPU takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing {
+    /// Once per step, after the instruction has executed. The fastest.
+    Instruction,
+    /// By one M-cycle on every memory access and internal delay, as they happen.
+    /// Internal delays after the instruction's last access are ticked after it.
+    MCycle,
+}
+
+/// What happened during a call to `Cpu::step`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepInfo {
+    /// The PC at the start of the step.
+    pub pc: u16,
+    /// The opcode executed, or `None` if no instruction was fetched
+    /// (an interrupt was dispatched, or the CPU is not running).
+    pub opcode: Option<u8>,
+    /// The T-cycles the step took.
+    pub cycles: u8,
+    /// The run state at the end of the step.
+    pub state: RunState,
+}
+
+/// Why one of the bounded `Cpu::run_*` methods returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The requested T-cycles elapsed.
+    CyclesElapsed,
+    /// A video frame ended.
+    FrameEnded,
+    /// The PC reached the requested address.
+    ReachedPc,
+    /// The PC reached the breakpoint at this address.
+    Breakpoint(u16),
+    /// The CPU was stopped by STOP, and the clock along with it,
+    /// so nothing happens until a joypad button is pressed.
+    Stopped,
+    /// The CPU is halted with no interrupt enabled in IE to wake it up.
+    HaltedForever,
+    /// The CPU locked up on an illegal opcode.
+    Locked,
+}
+
+/// The flags of the F register, decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagsState {
+    pub z: bool,
+    pub n: bool,
+    pub h: bool,
+    pub c: bool,
+}
 
-mod interrupts;
+/// A snapshot of the CPU's state, returned by `Cpu::state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuState {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub flags: FlagsState,
+    /// The Interrupt Master Enable flag.
+    pub ime: bool,
+    pub run_state: RunState,
+    /// The T-cycles taken since the CPU was created.
+    pub cycles: u64,
+}
 
-// ~ 1000 / 4194304, 4.194304 MHz = CPU freg (T-cycles), 
-// or 1.048576 MHz = M-cycles
-const CLOCK_CYCLE_NANOS: u64 = 1_000_000_000 / 41943044; 
+/// An error stepping the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuError {
+    /// An illegal opcode was executed at `pc`, locking the CPU up.
+    IllegalOpcode { opcode: u8, pc: u16 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::IllegalOpcode { opcode, pc } => {
+                write!(f, "illegal opcode {:#04x} at {:#06x}, the CPU locked up", opcode, pc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
 
 pub struct Cpu {
     regs: Regs,
-    sp: usize,
-    pc: usize,
+    pc: u16,
+    /// The instruction register, holding the opcode being executed.
+    ir: u8,
+    /// The Interrupt Master Enable flag.
     ime: bool,
-    div_ctrl: u16,
+    /// Set by EI, enables the IME once the following instruction has executed.
+    ime_scheduled: bool,
+    state: RunState,
+    /// Set by HALT when it fails to halt, the next opcode fetch won't increment the PC.
+    halt_bug: bool,
+    timing: Timing,
+    /// The T-cycles of the current step the rest of the system was already ticked by.
+    ticked: u8,
+    /// The T-cycles taken since the CPU was created.
+    cycles: u64,
+    tracer: Option<Tracer>,
+    debug_hook: Option<DebugHook>,
+    breakpoints: HashSet<u16>,
+}
+
+impl Default for Cpu {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Cpu {
@@ -25,92 +164,838 @@ impl Cpu {
     pub fn new() -> Self {
         Self {
             regs: Regs::default(),
-            sp: 0,
             pc: 0,
-            ime: true,
-            div_ctrl: 0,
+            ir: 0,
+            ime: false,
+            ime_scheduled: false,
+            state: RunState::Running,
+            halt_bug: false,
+            timing: Timing::Instruction,
+            ticked: 0,
+            cycles: 0,
+            tracer: None,
+            debug_hook: None,
+            breakpoints: HashSet::new(),
+        }
+    }
+
+    /// Sets the tracer logging every instruction before it executes, or disables tracing.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) {
+        self.tracer = tracer;
+    }
+
+    /// Sets the hook called after every `LD B, B`, or removes it.
+    pub fn set_debug_hook(&mut self, hook: Option<DebugHook>) {
+        self.debug_hook = hook;
+    }
+
+    /// Adds a breakpoint, stopping the `run_*` methods whenever the PC reaches `pc`.
+    pub fn add_breakpoint(&mut self, pc: u16) {
+        self.breakpoints.insert(pc);
+    }
+
+    /// Removes the breakpoint at `pc`. Returns whether there was one.
+    pub fn remove_breakpoint(&mut self, pc: u16) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    /// Removes every breakpoint.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// Returns a snapshot of the registers, flags, IME, run state and cycle counter.
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            a: self.regs.a(),
+            f: self.regs.f(),
+            b: self.regs.b(),
+            c: self.regs.c(),
+            d: self.regs.d(),
+            e: self.regs.e(),
+            h: self.regs.h(),
+            l: self.regs.l(),
+            sp: self.regs.sp(),
+            pc: self.pc,
+            flags: FlagsState {
+                z: self.regs.check_flags(Flags::Z),
+                n: self.regs.check_flags(Flags::N),
+                h: self.regs.check_flags(Flags::H),
+                c: self.regs.check_flags(Flags::C),
+            },
+            ime: self.ime,
+            run_state: self.state,
+            cycles: self.cycles,
+        }
+    }
+
+    /// Sets the PC.
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    /// Sets the instruction register, as if `opcode` was just fetched.
+    /// Instructions decode their operands from it. Only meant for the dispatch benchmark,
+    /// which executes instructions without fetching them.
+    #[doc(hidden)]
+    pub fn set_ir(&mut self, opcode: u8) {
+        self.ir = opcode;
+    }
+
+    /// Sets the 8 bit register `reg`.
+    pub fn set_reg8(&mut self, reg: Reg8, value: u8) {
+        self.regs.set_reg8(reg, value);
+    }
+
+    /// Sets the 16 bit register `reg`.
+    /// The lower nibble of F always reads back as zero, so it is masked out for AF.
+    pub fn set_reg16(&mut self, reg: Reg16, value: u16) {
+        let value = if reg == Reg16::AF { value & 0xfff0 } else { value };
+        self.regs.set_reg16(reg, value);
+    }
+
+    /// Sets the Interrupt Master Enable flag, cancelling a pending EI.
+    pub fn set_ime(&mut self, ime: bool) {
+        self.ime = ime;
+        self.ime_scheduled = false;
+    }
+
+    /// Sets the registers to the values the boot ROM of `model` leaves them with,
+    /// starting at the cartridge's entry point. Use with `Memory::skip_boot`.
+    pub fn skip_boot(&mut self, model: Model, memory: &Memory) {
+        let header_checksum = memory.read_byte(0x014d);
+        let [af, bc, de, hl] = model.post_boot_registers(header_checksum);
+        self.regs.set_af(af);
+        self.regs.set_bc(bc);
+        self.regs.set_de(de);
+        self.regs.set_hl(hl);
+        self.regs.set_sp(0xfffe);
+        self.pc = 0x0100;
+    }
+
+    /// Sets when the rest of the system is advanced by the cycles the CPU takes.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// Runs the CPU until it locks up.
+    pub fn run(&mut self, memory: &mut Memory) -> Result<(), CpuError> {
+        loop {
+            self.step(memory)?;
         }
     }
 
-    /// Runs the CPU.
-    pub fn run(&mut self, memory: &mut Memory) {
+    /// Runs the CPU for at least `cycles` T-cycles, finishing the last instruction,
+    /// or until a breakpoint.
+    pub fn run_for_cycles(
+        &mut self,
+        memory: &mut Memory,
+        cycles: u64,
+    ) -> Result<StopReason, CpuError> {
+        let end = self.cycles + cycles;
+        self.run_until(memory, |cpu, _| (cpu.cycles >= end).then_some(StopReason::CyclesElapsed))
+    }
+
+    /// Runs the CPU until the end of the current video frame, or until a breakpoint.
+    /// Frames are timed by the dots elapsed, so they keep their length in double speed.
+    /// Returns early if the CPU is stopped, as no dots elapse then.
+    pub fn run_until_frame(&mut self, memory: &mut Memory) -> Result<StopReason, CpuError> {
+        let frame = memory.dots() / DOTS_PER_FRAME;
+        self.run_until(memory, |cpu, memory| {
+            if memory.dots() / DOTS_PER_FRAME > frame {
+                Some(StopReason::FrameEnded)
+            } else if cpu.state == RunState::Stopped {
+                Some(StopReason::Stopped)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Runs the CPU until the PC reaches `pc`, or until a breakpoint.
+    /// At least one step is taken, so this can run a loop starting at `pc` once.
+    /// Returns early if the CPU can no longer reach `pc` on its own: when it is
+    /// stopped, locked up, or halted with no interrupt enabled.
+    /// It still never returns if the program loops forever elsewhere.
+    pub fn run_until_pc(&mut self, memory: &mut Memory, pc: u16) -> Result<StopReason, CpuError> {
+        self.run_until(memory, |cpu, memory| {
+            if cpu.pc == pc {
+                return Some(StopReason::ReachedPc);
+            }
+            match cpu.state {
+                RunState::Running => None,
+                RunState::Halted if memory.interrupts().any_enabled() => None,
+                RunState::Halted => Some(StopReason::HaltedForever),
+                RunState::Stopped => Some(StopReason::Stopped),
+                RunState::Locked => Some(StopReason::Locked),
+            }
+        })
+    }
+
+    /// Steps the CPU until `stop` returns why to stop, or the PC reaches a breakpoint.
+    /// Breakpoints are ignored on the steps that neither execute an instruction
+    /// nor move the PC, so a halted CPU doesn't hit the same one over and over.
+    fn run_until<F>(&mut self, memory: &mut Memory, mut stop: F) -> Result<StopReason, CpuError>
+    where
+        F: FnMut(&Cpu, &Memory) -> Option<StopReason>,
+    {
         loop {
-            self.step(memory);
+            let info = self.step(memory)?;
+            if let Some(reason) = stop(self, memory) {
+                return Ok(reason);
+            }
+            let moved = info.opcode.is_some() || info.pc != self.pc;
+            if moved && self.breakpoints.contains(&self.pc) {
+                return Ok(StopReason::Breakpoint(self.pc));
+            }
         }
     }
 
-    fn step(&mut self, memory: &mut Memory) {
-        self.decode_execute(memory);
+    /// Dispatches a pending interrupt or executes the next instruction,
+    /// then advances the rest of the system by the T-cycles it took.
+    /// Returns an error if the instruction locked the CPU up.
+    pub fn step(&mut self, memory: &mut Memory) -> Result<StepInfo, CpuError> {
+        let pc = self.pc;
+        self.ticked = 0;
+        let (opcode, cycles) = self.decode_execute(memory);
+        self.cycles += cycles as u64;
+
+        // The clock is stopped along with the CPU.
+        if self.state != RunState::Stopped {
+            memory.tick(cycles - self.ticked);
+        }
+
+        if opcode == Some(LD_B_B) {
+            let state = self.state();
+            if let Some(hook) = &mut self.debug_hook {
+                hook(&state);
+            }
+        }
+
+        match opcode {
+            Some(opcode) if self.state == RunState::Locked => {
+                Err(CpuError::IllegalOpcode { opcode, pc })
+            }
+            _ => Ok(StepInfo { pc, opcode, cycles, state: self.state }),
+        }
     }
 
-    /// Matches (decodes) the given opcode and executes it.
-    fn decode_execute(&mut self, memory: &mut Memory) {
-        use instructions::lookup::{ InstructionAccess, INSTRS };
+    /// Dispatches a pending interrupt, or matches (decodes) the next opcode and executes it.
+    /// Returns the executed opcode, if any, and the T-cycles taken.
+    fn decode_execute(&mut self, memory: &mut Memory) -> (Option<u8>, u8) {
+        match self.state {
+            RunState::Locked => return (None, 4),
+            RunState::Stopped => {
+                // Any selected button line going low, or a joypad interrupt, wakes the CPU up.
+                if !memory.joypad_wakes() {
+                    return (None, 4);
+                }
+                self.state = RunState::Running;
+            }
+            _ => {}
+        }
 
-        #[cfg(debug_assertions)] {
-            println!();
-            println!("PC: {:#06x}", self.pc);
-            println!("SP: {:#06x}", self.sp);
-            println!("IME: {}", self.ime);
-            println!("IF: {:#010b}", memory.read_byte(0xff0f));
-            println!("IE: {:#010b}", memory.read_byte(0xffff));
-            println!("DIV: {:#04x}", memory.read_byte(0xff04));
-            println!("CPU flags (f): {:#010b}", self.regs.f());
+        /* Interrupt handling. */
+        if memory.interrupts().pending().is_some() {
+            // Any pending interrupt wakes the CPU up, even when the IME is unset.
+            self.state = RunState::Running;
+            if self.ime {
+                return (None, interrupts::dispatch(self, memory));
+            }
         }
 
-        let opcode = self.consume_byte(memory);
-        if let Some(instr_access) = INSTRS.get(&opcode) {
-            match instr_access {
-                InstructionAccess::Cpu(instr) => instr(self),
-                InstructionAccess::CpuWithMemory(instr) => instr(self, memory),
+        if self.state == RunState::Halted {
+            return (None, 4);
+        }
+
+        if let Some(mut tracer) = self.tracer.take() {
+            // Tracing stops if the trace can't be written.
+            if tracer.log(self, memory).is_ok() {
+                self.tracer = Some(tracer);
             }
+        }
+
+        // EI only takes effect after the instruction following it, unless DI cancels it.
+        let enable_ime = self.ime_scheduled;
+
+        self.ir = self.consume_byte(memory);
+        let cycles = instructions::lookup::INSTRS[self.ir as usize](self, memory);
+
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+
+        (Some(self.ir), cycles)
+    }
+
+    /// Reads the byte at `addr`, taking one M-cycle.
+    fn read(&mut self, memory: &mut Memory, addr: u16) -> u8 {
+        self.tick_m_cycle(memory);
+        memory.read_byte(addr)
+    }
+
+    /// Writes `byte` to `addr`, taking one M-cycle.
+    fn write(&mut self, memory: &mut Memory, addr: u16, byte: u8) {
+        self.tick_m_cycle(memory);
+        memory.write_byte(addr, byte);
+    }
+
+    /// Takes one M-cycle without accessing memory.
+    fn idle(&mut self, memory: &mut Memory) {
+        self.tick_m_cycle(memory);
+    }
+
+    /// Ticks the rest of the system by one M-cycle right away, when M-cycle timing is used.
+    fn tick_m_cycle(&mut self, memory: &mut Memory) {
+        if self.timing == Timing::MCycle {
+            memory.tick(4);
+            self.ticked += 4;
+        }
+    }
+
+    /// Ticks the rest of the system up to `cycles` T-cycles into the step right away,
+    /// whatever the timing, for effects that must follow those cycles in both timings.
+    fn catch_up(&mut self, memory: &mut Memory, cycles: u8) {
+        memory.tick(cycles - self.ticked);
+        self.ticked = cycles;
+    }
+
+    /// Returns the byte at the current PC and increments it,
+    /// unless the HALT bug keeps it in place for this read.
+    fn consume_byte(&mut self, memory: &mut Memory) -> u8 {
+        let byte = self.read(memory, self.pc);
+        if self.halt_bug {
+            self.halt_bug = false;
         } else {
-            todo!("{:#04x}", opcode);
-        }
-
-        // Increment the Divider Register.
-        if self.div_ctrl > 255 {
-            let new_div = memory.read_byte(0xff04).wrapping_add(1);
-            memory.write_byte(0xff04, new_div);
-            self.div_ctrl = 0;
-        }
-
-        /* Interupt handling. */
-        if self.ime {
-            // TODO: check for and handle potential interrupts.
-
-            // Get the byte representing the interrupt requests.
-            let interrupt_requests = memory.read_byte(0xff0f);
-            let interrupt_enables = memory.read_byte(0xffff);
-
-            /*
-            * The following bits are checked in priority of LSB to MSB:
-            *  - Bit 0: V-Blank
-            *  - Bit 1: LCD STAT
-            *  - Bit 2: Timer
-            *  - Bit 3: Serial
-            *  - Bit 4: Joypad
-            */
-            // Check the bits.
-
-            if interrupt_requests & interrupt_enables & 1 == 1 { // V-Blank
-                interrupts::vblank_interrupt_handler(self, memory);
-            } else if interrupt_requests & interrupt_enables & (1 << 1) == (1 << 1) { // LCD STAT
-                interrupts::lcd_stat_interrupt_handler(self, memory);
-            } else if interrupt_requests & interrupt_enables & (1 << 2) == (1 << 2) { // Timer
-                interrupts::timer_interrupt_handler(self, memory);
-            } else if interrupt_requests & interrupt_enables & (1 << 3) == (1 << 3) { // Serial
-                interrupts::serial_interrupt_handler(self, memory);
-            } else if interrupt_requests & interrupt_enables & (1 << 4) == (1 << 4) { // Joypad
-                interrupts::joypad_interrupt_handler(self, memory);
-            }
+            self.pc = self.pc.wrapping_add(1);
         }
+        byte
     }
 
-    /// Returns the byte at the current PC and increments it.
-    fn consume_byte(&mut self, memory: &Memory) -> u8 {
-        self.pc += 1;
-        memory.read_byte(self.pc - 1)
+    /// Returns the little endian word at the current PC and increments it by two.
+    fn consume_word(&mut self, memory: &mut Memory) -> u16 {
+        let lower = self.consume_byte(memory) as u16;
+        let upper = (self.consume_byte(memory) as u16) << 8;
+        upper | lower
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::interrupts::{ Interrupt, IE, IF };
+    use crate::joypad::{ Button, P1 };
+    use crate::speed::KEY1;
+    use crate::timer;
+
+    /// Returns a CPU about to execute `program`, loaded at 0x0150,
+    /// and memory with the given IE and IF.
+    fn setup(program: &[u8], ie: u8, if_: u8) -> (Cpu, Memory) {
+        let mut cpu = Cpu::new();
+        cpu.pc = 0x0150;
+        cpu.regs.set_sp(0xfffe);
+
+        let mut memory = Memory::new();
+        for (i, &byte) in program.iter().enumerate() {
+            memory.write_byte(0x0150 + i as u16, byte);
+        }
+        memory.write_byte(IE, ie);
+        memory.write_byte(IF, if_);
+        (cpu, memory)
+    }
+
+    #[test]
+    fn halt_waits_for_a_pending_interrupt_even_without_the_ime() {
+        let (mut cpu, mut memory) = setup(&[0x76, 0x04], 0x01, 0x00); // HALT; INC B
+
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.state, RunState::Halted);
+        assert_eq!(cpu.pc, 0x0151);
+
+        memory.write_byte(IF, 0x01);
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.state, RunState::Running);
+        assert_eq!(cpu.regs.b(), 1);
+        assert_eq!(cpu.pc, 0x0152);
+        assert_eq!(memory.read_byte(IF), 0xe1);
+    }
+
+    #[test]
+    fn halt_bug_executes_the_next_opcode_twice() {
+        let (mut cpu, mut memory) = setup(&[0x76, 0x04], 0x01, 0x01); // HALT; INC B
+
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.state, RunState::Running);
+
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.pc, 0x0151);
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.regs.b(), 2);
+        assert_eq!(cpu.pc, 0x0152);
+    }
+
+    #[test]
+    fn halt_bug_after_ei_returns_to_the_halt() {
+        let (mut cpu, mut memory) = setup(&[0xfb, 0x76], 0x01, 0x01); // EI; HALT
+
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.pc, 0x40);
+        assert!(!cpu.halt_bug);
+        assert_eq!(memory.read_byte(0xfffd), 0x01);
+        assert_eq!(memory.read_byte(0xfffc), 0x51);
+    }
+
+    #[test]
+    fn stop_resets_div_and_waits_for_the_joypad() {
+        let (mut cpu, mut memory) = setup(&[0x10, 0x00, 0x04], 0x00, 0x00); // STOP; INC B
+        for _ in 0..0x10 {
+            memory.tick(0xff);
+        }
+        assert_ne!(memory.read_byte(timer::DIV), 0);
+        memory.write_byte(P1, 0x10);
+
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.state, RunState::Stopped);
+        assert_eq!(memory.read_byte(timer::DIV), 0);
+        assert_eq!(cpu.pc, 0x0152);
+
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.state, RunState::Stopped);
+        assert_eq!(cpu.regs.b(), 0);
+
+        // Pressing A pulls its line low.
+        memory.press_button(Button::A);
+        cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.state, RunState::Running);
+        assert_eq!(cpu.regs.b(), 1);
+    }
+
+    #[test]
+    fn stop_keeps_waiting_with_no_button_line_selected() {
+        // LD A, 0x30; LDH (P1), A; STOP; INC B
+        let program = [0x3e, 0x30, 0xe0, 0x00, 0x10, 0x00, 0x04];
+        let (mut cpu, mut memory) = setup(&program, 0x00, 0x00);
+        memory.skip_boot(Model::Dmg);
+        cpu.skip_boot(Model::Dmg, &memory);
+        cpu.pc = 0x0150;
+
+        for _ in 0..8 {
+            cpu.step(&mut memory).unwrap();
+        }
+        assert_eq!(memory.read_byte(P1), 0xff);
+        assert_eq!(cpu.state, RunState::Stopped);
+        assert_eq!(cpu.regs.b(), 0x00);
+
+        // Start is not on a selected line.
+        memory.press_button(Button::Start);
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.state, RunState::Stopped);
+
+        // A requested joypad interrupt wakes the CPU up, even if it is not enabled.
+        memory.interrupts_mut().request(Interrupt::Joypad);
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.state, RunState::Running);
+        assert_eq!(cpu.regs.b(), 0x01);
+    }
+
+    #[test]
+    fn stop_switches_a_cgb_to_double_speed_once_armed() {
+        // LD A, 0x01; LDH (KEY1), A; STOP; INC B; INC B
+        let program = [0x3e, 0x01, 0xe0, 0x4d, 0x10, 0x00, 0x04, 0x04];
+        for timing in [Timing::Instruction, Timing::MCycle] {
+            let (mut cpu, mut memory) = setup(&program, 0x00, 0x00);
+            memory.skip_boot(Model::Cgb);
+            cpu.set_timing(timing);
+
+            cpu.step(&mut memory).unwrap();
+            cpu.step(&mut memory).unwrap();
+            let info = cpu.step(&mut memory).unwrap();
+            assert_eq!(info.cycles, 8, "{:?}", timing);
+            assert_eq!(cpu.state, RunState::Running);
+            assert!(memory.double_speed());
+            assert_eq!(memory.read_byte(KEY1), 0xfe);
+
+            // The CPU keeps running, with only half as many dots elapsing per cycle.
+            let dots = memory.dots();
+            let info = cpu.step(&mut memory).unwrap();
+            assert_eq!(cpu.regs.b(), 1);
+            assert_eq!(info.cycles, 4);
+            assert_eq!(memory.dots() - dots, 2, "{:?}", timing);
+        }
+    }
+
+    #[test]
+    fn stop_never_switches_the_speed_of_a_dmg() {
+        // LD A, 0x01; LDH (KEY1), A; STOP
+        let (mut cpu, mut memory) = setup(&[0x3e, 0x01, 0xe0, 0x4d, 0x10, 0x00], 0x00, 0x00);
+        memory.skip_boot(Model::Dmg);
+
+        for _ in 0..3 {
+            cpu.step(&mut memory).unwrap();
+        }
+        assert_eq!(cpu.state, RunState::Stopped);
+        assert!(!memory.double_speed());
+        assert_eq!(memory.read_byte(KEY1), 0xff);
+    }
+
+    #[test]
+    fn illegal_opcodes_lock_the_cpu_up() {
+        let illegal = [0xd3, 0xdb, 0xdd, 0xe3, 0xe4, 0xeb, 0xec, 0xed, 0xf4, 0xfc, 0xfd];
+        for opcode in illegal {
+            let (mut cpu, mut memory) = setup(&[opcode], 0x01, 0x00);
+            cpu.ime = true;
+
+            assert_eq!(
+                cpu.step(&mut memory),
+                Err(CpuError::IllegalOpcode { opcode, pc: 0x0150 }),
+            );
+            assert_eq!(cpu.state, RunState::Locked);
+
+            // Not even an interrupt gets it out.
+            memory.write_byte(IF, 0x01);
+            let info = cpu.step(&mut memory).unwrap();
+
+            assert_eq!(info.opcode, None);
+            assert_eq!(info.state, RunState::Locked);
+            assert_eq!(cpu.pc, 0x0151);
+        }
+    }
+
+    #[test]
+    fn step_returns_the_cycles_taken() {
+        // NOP; RLC B; RLC (HL); JR NZ, +0; JR Z, +0
+        // RLC (HL) rotates the zero at 0x0000, setting Z.
+        let program = [0x00, 0xcb, 0x00, 0xcb, 0x06, 0x20, 0x00, 0x28, 0x00];
+        let (mut cpu, mut memory) = setup(&program, 0x00, 0x00);
+
+        let cycles: Vec<_> = (0..5)
+            .map(|_| cpu.step(&mut memory).unwrap().cycles)
+            .collect();
+
+        assert_eq!(cycles, [4, 8, 16, 8, 12]);
+        assert_eq!(cpu.pc, 0x0159);
+    }
+
+    #[test]
+    fn the_clock_drives_the_timer() {
+        // 64 NOPs with TIMA incremented every 16 T-cycles.
+        let (mut cpu, mut memory) = setup(&[0x00; 64], 0x00, 0x00);
+        memory.write_byte(timer::TAC, 0x05);
+
+        for _ in 0..64 {
+            cpu.step(&mut memory).unwrap();
+        }
+
+        assert_eq!(memory.read_byte(timer::TIMA), 16);
+        assert_eq!(memory.read_byte(timer::DIV), 1);
+    }
+
+    #[test]
+    fn m_cycle_timing_ticks_before_each_access() {
+        // LD A, (FF00+0x05) reads TIMA in its third M-cycle.
+        // Starting 4 T-cycles in, that is when TIMA is incremented every 16 T-cycles.
+        for (timing, tima_read) in [(Timing::Instruction, 0), (Timing::MCycle, 1)] {
+            let (mut cpu, mut memory) = setup(&[0xf0, 0x05], 0x00, 0x00);
+            cpu.set_timing(timing);
+            memory.write_byte(timer::TAC, 0x05);
+            memory.tick(4);
+
+            let info = cpu.step(&mut memory).unwrap();
+
+            assert_eq!(cpu.regs.a(), tima_read, "{:?}", timing);
+            assert_eq!(info.cycles, 12);
+            assert_eq!(memory.read_byte(timer::TIMA), 1);
+        }
+    }
+
+    #[test]
+    fn m_cycle_timing_pops_after_the_condition_of_ret_cc() {
+        // RET NZ with the stack at TIMA reads it in its third M-cycle, after the
+        // internal M-cycle checking the condition. That is when TIMA is incremented.
+        let (mut cpu, mut memory) = setup(&[0xc0], 0x00, 0x00);
+        cpu.set_timing(Timing::MCycle);
+        cpu.regs.set_sp(timer::TIMA);
+        memory.write_byte(timer::TAC, 0x05);
+        memory.tick(4);
+
+        let info = cpu.step(&mut memory).unwrap();
+
+        assert_eq!(cpu.pc, 0x0001);
+        assert_eq!(info.cycles, 20);
+        assert_eq!(cpu.regs.sp(), timer::TIMA + 2);
+    }
+
+    #[test]
+    fn both_timings_tick_by_the_same_cycles() {
+        // PUSH BC; CALL 0x0160; (0x0160) RST 0x08; (0x0008) POP HL
+        let mut program = [0x00; 0x11];
+        program[..4].copy_from_slice(&[0xc5, 0xcd, 0x60, 0x01]);
+        program[0x10] = 0xcf;
+
+        for timing in [Timing::Instruction, Timing::MCycle] {
+            let (mut cpu, mut memory) = setup(&program, 0x00, 0x00);
+            memory.write_byte(0x0008, 0xe1);
+            cpu.set_timing(timing);
+
+            let cycles: u32 = (0..4)
+                .map(|_| cpu.step(&mut memory).unwrap().cycles as u32)
+                .sum();
+
+            assert_eq!(cycles, 16 + 24 + 16 + 12);
+            assert_eq!(memory.read_byte(timer::DIV), 0);
+            assert_eq!(cpu.regs.hl(), 0x0161);
+            memory.tick(188); // The rest of the first 256 T-cycles.
+            assert_eq!(memory.read_byte(timer::DIV), 1, "{:?}", timing);
+        }
+    }
+
+    #[test]
+    fn both_timings_tick_stop_before_it_resets_div() {
+        for timing in [Timing::Instruction, Timing::MCycle] {
+            let (mut cpu, mut memory) = setup(&[0x10, 0x00], 0x00, 0x00); // STOP
+            cpu.set_timing(timing);
+            memory.write_byte(timer::TAC, 0x05);
+            memory.tick(4);
+
+            let info = cpu.step(&mut memory).unwrap();
+
+            // Bit 3 of the counter was set by the 8 T-cycles of STOP, so resetting it
+            // incremented TIMA.
+            assert_eq!(info.cycles, 8);
+            assert_eq!(cpu.state, RunState::Stopped);
+            assert_eq!(memory.read_byte(timer::TIMA), 1, "{:?}", timing);
+            memory.tick(16);
+            assert_eq!(memory.read_byte(timer::TIMA), 2, "{:?}", timing);
+        }
+    }
+
+    #[test]
+    fn skip_boot_starts_at_the_entry_point_with_the_post_boot_state() {
+        let mut cpu = Cpu::new();
+        let mut memory = Memory::new();
+        memory.load_rom(&[0x00; 0x150]);
+        memory.write_byte(0x014d, 0x42);
+
+        memory.skip_boot(Model::Dmg);
+        cpu.skip_boot(Model::Dmg, &memory);
+
+        assert_eq!(cpu.pc, 0x0100);
+        assert_eq!(cpu.regs.sp(), 0xfffe);
+        assert_eq!(cpu.regs.af(), 0x01b0);
+        assert_eq!(cpu.regs.bc(), 0x0013);
+        assert_eq!(cpu.regs.de(), 0x00d8);
+        assert_eq!(cpu.regs.hl(), 0x014d);
+        assert_eq!(memory.read_byte(timer::DIV), 0xab);
+        assert_eq!(memory.read_byte(timer::TAC), 0xf8);
+        assert_eq!(memory.read_byte(0xff40), 0x91);
+    }
+
+    #[test]
+    fn skip_boot_sets_the_registers_of_each_model() {
+        // (model, AF, BC, DE, HL) with a zero header checksum.
+        let models = [
+            (Model::Dmg0, 0x0100, 0xff13, 0x00c1, 0x8403),
+            (Model::Dmg, 0x0180, 0x0013, 0x00d8, 0x014d),
+            (Model::Mgb, 0xff80, 0x0013, 0x00d8, 0x014d),
+            (Model::Sgb, 0x0100, 0x0014, 0x0000, 0xc060),
+            (Model::Sgb2, 0xff00, 0x0014, 0x0000, 0xc060),
+            (Model::Cgb, 0x1180, 0x0000, 0xff56, 0x000d),
+        ];
+        for (model, af, bc, de, hl) in models {
+            let mut cpu = Cpu::new();
+            let memory = Memory::new();
+
+            cpu.skip_boot(model, &memory);
+
+            assert_eq!(
+                [cpu.regs.af(), cpu.regs.bc(), cpu.regs.de(), cpu.regs.hl()],
+                [af, bc, de, hl],
+                "{:?}", model,
+            );
+        }
+    }
+
+    #[test]
+    fn pc_and_sp_wrap_around_the_address_space() {
+        let mut cpu = Cpu::new();
+        let mut memory = Memory::new();
+
+        // LD BC, 0x1234 straddling 0xffff and 0x0000.
+        cpu.pc = 0xfffe;
+        memory.write_byte(0xfffe, 0x01);
+        memory.write_byte(0xffff, 0x34);
+        memory.write_byte(0x0000, 0x12);
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.regs.bc(), 0x1234);
+        assert_eq!(cpu.pc, 0x0001);
+
+        // PUSH BC with SP at 0x0000 writes IE and 0xfffe.
+        cpu.regs.set_sp(0x0000);
+        memory.write_byte(0x0001, 0xc5);
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.regs.sp(), 0xfffe);
+        assert_eq!(memory.read_byte(0xffff), 0x12);
+        assert_eq!(memory.read_byte(0xfffe), 0x34);
+
+        // JR -4 at 0x0002 lands back on 0x0000.
+        memory.write_byte(0x0002, 0x18);
+        memory.write_byte(0x0003, 0xfc);
+        cpu.step(&mut memory).unwrap();
+        assert_eq!(cpu.pc, 0x0000);
+    }
+
+    #[test]
+    fn state_reflects_the_setters_and_the_steps_taken() {
+        let (mut cpu, mut memory) = setup(&[0x37, 0x76], 0x00, 0x00); // SCF; HALT
+        cpu.set_reg16(Reg16::AF, 0x12ff);
+        cpu.set_reg8(Reg8::L, 0x34);
+        cpu.set_reg16(Reg16::SP, 0xc000);
+        cpu.set_ime(true);
+
+        cpu.step(&mut memory).unwrap();
+        cpu.step(&mut memory).unwrap();
+        let state = cpu.state();
+
+        assert_eq!((state.a, state.f), (0x12, 0x90));
+        assert_eq!(state.flags, FlagsState { z: true, n: false, h: false, c: true });
+        assert_eq!(state.l, 0x34);
+        assert_eq!(state.sp, 0xc000);
+        assert_eq!(state.pc, 0x0152);
+        assert!(state.ime);
+        assert_eq!(state.run_state, RunState::Halted);
+        assert_eq!(state.cycles, 8);
+
+        cpu.set_pc(0x0200);
+        assert_eq!(cpu.state().pc, 0x0200);
+    }
+
+    #[test]
+    fn the_debug_hook_fires_after_every_ld_b_b() {
+        // LD B, 3; LD B, B; INC B; LD B, B
+        let (mut cpu, mut memory) = setup(&[0x06, 0x03, 0x40, 0x04, 0x40], 0x00, 0x00);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let hook_seen = Rc::clone(&seen);
+        cpu.set_debug_hook(Some(Box::new(move |state: &CpuState| {
+            hook_seen.borrow_mut().push((state.b, state.pc));
+        })));
+
+        for _ in 0..4 {
+            cpu.step(&mut memory).unwrap();
+        }
+
+        assert_eq!(*seen.borrow(), [(3, 0x0153), (4, 0x0155)]);
+    }
+
+    #[test]
+    fn run_for_cycles_finishes_the_last_instruction() {
+        let (mut cpu, mut memory) = setup(&[0x04, 0x18, 0xfd], 0x00, 0x00); // INC B; JR -3
+
+        let reason = cpu.run_for_cycles(&mut memory, 30).unwrap();
+
+        assert_eq!(reason, StopReason::CyclesElapsed);
+        assert_eq!(cpu.cycles, 32);
+        assert_eq!(cpu.regs.b(), 2);
+    }
+
+    #[test]
+    fn breakpoints_stop_every_run_until_removed() {
+        let (mut cpu, mut memory) = setup(&[0x04, 0x18, 0xfd], 0x00, 0x00); // INC B; JR -3
+        cpu.add_breakpoint(0x0151);
+
+        assert_eq!(cpu.run_for_cycles(&mut memory, 1000), Ok(StopReason::Breakpoint(0x0151)));
+        assert_eq!(cpu.regs.b(), 1);
+        assert_eq!(cpu.run_until_pc(&mut memory, 0x0200), Ok(StopReason::Breakpoint(0x0151)));
+        assert_eq!(cpu.regs.b(), 2);
+
+        assert!(cpu.remove_breakpoint(0x0151));
+        assert!(!cpu.remove_breakpoint(0x0151));
+        assert_eq!(cpu.run_for_cycles(&mut memory, 32), Ok(StopReason::CyclesElapsed));
+    }
+
+    #[test]
+    fn a_halted_cpu_hits_its_breakpoint_once() {
+        let (mut cpu, mut memory) = setup(&[0x76, 0x04], 0x00, 0x00); // HALT; INC B
+        cpu.add_breakpoint(0x0151);
+
+        assert_eq!(cpu.run_for_cycles(&mut memory, 100), Ok(StopReason::Breakpoint(0x0151)));
+        assert_eq!(cpu.run_for_cycles(&mut memory, 100), Ok(StopReason::CyclesElapsed));
+        assert_eq!(cpu.state, RunState::Halted);
+    }
+
+    #[test]
+    fn run_until_pc_takes_at_least_one_step() {
+        let (mut cpu, mut memory) = setup(&[0x04, 0x18, 0xfd], 0x00, 0x00); // INC B; JR -3
+
+        assert_eq!(cpu.run_until_pc(&mut memory, 0x0150), Ok(StopReason::ReachedPc));
+        assert_eq!(cpu.regs.b(), 1);
+        assert_eq!(cpu.run_until_pc(&mut memory, 0x0150), Ok(StopReason::ReachedPc));
+        assert_eq!(cpu.regs.b(), 2);
+    }
+
+    #[test]
+    fn run_until_pc_returns_once_the_cpu_is_stuck() {
+        let (mut cpu, mut memory) = setup(&[0x76, 0x00], 0x00, 0x00); // HALT
+        assert_eq!(cpu.run_until_pc(&mut memory, 0x0200), Ok(StopReason::HaltedForever));
+
+        let (mut cpu, mut memory) = setup(&[0x10, 0x00], 0x00, 0x00); // STOP
+        assert_eq!(cpu.run_until_pc(&mut memory, 0x0200), Ok(StopReason::Stopped));
+
+        let (mut cpu, mut memory) = setup(&[0xd3], 0x00, 0x00); // Illegal
+        assert!(cpu.run_until_pc(&mut memory, 0x0200).is_err());
+        assert_eq!(cpu.run_until_pc(&mut memory, 0x0200), Ok(StopReason::Locked));
+    }
+
+    #[test]
+    fn run_until_pc_waits_for_an_enabled_interrupt_while_halted() {
+        // HALT; INC B, with the timer interrupt enabled and handled at 0x0050.
+        let (mut cpu, mut memory) = setup(&[0x76, 0x04], 0x04, 0x00);
+        cpu.ime = true;
+        memory.write_byte(timer::TAC, 0x05);
+
+        assert_eq!(cpu.run_until_pc(&mut memory, 0x0050), Ok(StopReason::ReachedPc));
+    }
+
+    #[test]
+    fn run_until_frame_runs_for_one_frame_of_dots() {
+        let (mut cpu, mut memory) = setup(&[0x18, 0xfe], 0x00, 0x00); // JR -2
+
+        assert_eq!(cpu.run_until_frame(&mut memory), Ok(StopReason::FrameEnded));
+        assert!((DOTS_PER_FRAME..DOTS_PER_FRAME + 12).contains(&memory.dots()));
+        assert_eq!(cpu.cycles, memory.dots());
+
+        assert_eq!(cpu.run_until_frame(&mut memory), Ok(StopReason::FrameEnded));
+        assert!((2 * DOTS_PER_FRAME..2 * DOTS_PER_FRAME + 12).contains(&memory.dots()));
+    }
+
+    #[test]
+    fn frames_take_twice_the_cycles_in_double_speed() {
+        // LD A, 0x01; LDH (KEY1), A; STOP; JR -2
+        let program = [0x3e, 0x01, 0xe0, 0x4d, 0x10, 0x00, 0x18, 0xfe];
+        let (mut cpu, mut memory) = setup(&program, 0x00, 0x00);
+        memory.skip_boot(Model::Cgb);
+
+        assert_eq!(cpu.run_until_frame(&mut memory), Ok(StopReason::FrameEnded));
+        assert!(memory.double_speed());
+        assert!(cpu.cycles > 2 * DOTS_PER_FRAME - 32);
+    }
+
+    #[test]
+    fn run_until_frame_returns_once_the_cpu_is_stopped() {
+        let (mut cpu, mut memory) = setup(&[0x10, 0x00], 0x00, 0x00); // STOP
+        memory.skip_boot(Model::Dmg);
+
+        assert_eq!(cpu.run_until_frame(&mut memory), Ok(StopReason::Stopped));
+        assert_eq!(cpu.state, RunState::Stopped);
+    }
+}
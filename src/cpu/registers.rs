@@ -7,12 +7,63 @@ impl Flags {
     pub const C: u8 = 1 << 4;
 }
 
+/// The 8 bit registers, as encoded in the `r` operand field of an opcode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reg8 {
+    B, C, D, E, H, L, A,
+}
+
+impl Reg8 {
+    /// Decodes the 3 bit `r` operand field of an opcode.
+    /// Returns `None` for 0b110, which refers to the byte at (HL) rather than a register.
+    pub fn from_r(bits: u8) -> Option<Self> {
+        match bits & 0b111 {
+            0 => Some(Reg8::B),
+            1 => Some(Reg8::C),
+            2 => Some(Reg8::D),
+            3 => Some(Reg8::E),
+            4 => Some(Reg8::H),
+            5 => Some(Reg8::L),
+            6 => None,
+            _ => Some(Reg8::A),
+        }
+    }
+}
+
+/// The 16 bit registers, as encoded in the `rp` and `rp2` operand fields of an opcode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reg16 {
+    AF, BC, DE, HL, SP,
+}
+
+impl Reg16 {
+    /// Decodes the 2 bit `rp` operand field used by the 16 bit loads and arithmetic.
+    pub fn from_rp(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => Reg16::BC,
+            1 => Reg16::DE,
+            2 => Reg16::HL,
+            _ => Reg16::SP,
+        }
+    }
+
+    /// Decodes the 2 bit `rp2` operand field used by PUSH and POP, 
+    /// where AF takes the place of SP.
+    pub fn from_rp2(bits: u8) -> Self {
+        match Self::from_rp(bits) {
+            Reg16::SP => Reg16::AF,
+            reg => reg,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Regs {
     a: u8, f: u8,
     b: u8, c: u8,
     d: u8, e: u8,
     h: u8, l: u8,
+    sp: u16,
 }
 
 impl Regs {
@@ -24,13 +75,13 @@ impl Regs {
     /// Sets the given flags in the F register.
     /// Does not modify the other flags.
     pub fn set_flags(&mut self, flags: u8) {
-        self.f = self.f | flags;
+        self.f |= flags;
     }
     
     /// Resets the given flags in the F register.
     /// Does not modify the other flags.
     pub fn reset_flags(&mut self, flags: u8) {
-        self.f = self.f & (flags ^ 0xff);
+        self.f &= !flags;
     }
 
     /* Getters */
@@ -47,6 +98,31 @@ impl Regs {
     pub fn bc(&self) -> u16 { ((self.b as u16) << 8) | self.c as u16 }
     pub fn de(&self) -> u16 { ((self.d as u16) << 8) | self.e as u16 }
     pub fn hl(&self) -> u16 { ((self.h as u16) << 8) | self.l as u16 }
+    pub fn sp(&self) -> u16 { self.sp }
+
+    /// Returns the value of the 8 bit register `reg`.
+    pub fn reg8(&self, reg: Reg8) -> u8 {
+        match reg {
+            Reg8::B => self.b,
+            Reg8::C => self.c,
+            Reg8::D => self.d,
+            Reg8::E => self.e,
+            Reg8::H => self.h,
+            Reg8::L => self.l,
+            Reg8::A => self.a,
+        }
+    }
+
+    /// Returns the value of the 16 bit register `reg`.
+    pub fn reg16(&self, reg: Reg16) -> u16 {
+        match reg {
+            Reg16::AF => self.af(),
+            Reg16::BC => self.bc(),
+            Reg16::DE => self.de(),
+            Reg16::HL => self.hl(),
+            Reg16::SP => self.sp,
+        }
+    }
 
     /* Setters */
     pub fn set_a(&mut self, x: u8) { self.a = x; }
@@ -77,6 +153,32 @@ impl Regs {
         self.h = (x >> 8) as u8;
         self.l = (x & 0xff) as u8;
     }
+
+    pub fn set_sp(&mut self, x: u16) { self.sp = x; }
+
+    /// Sets the 8 bit register `reg` to `x`.
+    pub fn set_reg8(&mut self, reg: Reg8, x: u8) {
+        match reg {
+            Reg8::B => self.b = x,
+            Reg8::C => self.c = x,
+            Reg8::D => self.d = x,
+            Reg8::E => self.e = x,
+            Reg8::H => self.h = x,
+            Reg8::L => self.l = x,
+            Reg8::A => self.a = x,
+        }
+    }
+
+    /// Sets the 16 bit register `reg` to `x`.
+    pub fn set_reg16(&mut self, reg: Reg16, x: u16) {
+        match reg {
+            Reg16::AF => self.set_af(x),
+            Reg16::BC => self.set_bc(x),
+            Reg16::DE => self.set_de(x),
+            Reg16::HL => self.set_hl(x),
+            Reg16::SP => self.sp = x,
+        }
+    }
 }
 
 // Associated functions
@@ -85,10 +187,5 @@ impl Regs {
     pub fn check_bits_8bit(register: u8, bits: u8) -> bool {
         register & bits == bits
     }
-    
-    /// Returns whether the `bits` in `register` are set for 16 bit registers.
-    pub fn check_bits_16bit(register: u16, bits: u16) -> bool {
-        register & bits == bits
-    }
 }
 
@@ -0,0 +1,88 @@
+//! An instruction trace in the format of Gameboy Doctor.
+//!
+//! Every instruction is logged before it executes as a line such as
+//! `A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02`,
+//! which can be diffed against the logs from https://github.com/robert/gameboy-doctor.
+//! Those expect the boot ROM to be skipped and LY to always read 0x90.
+
+use std::io::{ self, Write };
+
+use crate::cpu::Cpu;
+use crate::memory::Memory;
+
+pub struct Tracer {
+    out: Box<dyn Write>,
+}
+
+impl Tracer {
+    /// Returns a new instance of `Tracer`, writing the trace to `out`.
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self { out: Box::new(out) }
+    }
+
+    /// Writes the line of the instruction `cpu` is about to execute.
+    pub fn log(&mut self, cpu: &Cpu, memory: &Memory) -> io::Result<()> {
+        let state = cpu.state();
+        let pc = state.pc;
+        writeln!(
+            self.out,
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} \
+             SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            state.a, state.f, state.b, state.c, state.d, state.e, state.h, state.l,
+            state.sp, pc,
+            memory.read_byte(pc),
+            memory.read_byte(pc.wrapping_add(1)),
+            memory.read_byte(pc.wrapping_add(2)),
+            memory.read_byte(pc.wrapping_add(3)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::model::Model;
+
+    /// A writer whose contents can still be read once it is handed to a `Tracer`.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn logs_every_instruction_in_the_gameboy_doctor_format() {
+        let mut cpu = Cpu::new();
+        let mut memory = Memory::new();
+        memory.load_rom(&[0x00; 0x150]);
+        memory.write_byte(0x0101, 0xc3); // JP 0x0213
+        memory.write_byte(0x0102, 0x13);
+        memory.write_byte(0x0103, 0x02);
+        memory.skip_boot(Model::Dmg);
+        cpu.skip_boot(Model::Dmg, &memory);
+
+        let buffer = SharedBuffer::default();
+        cpu.set_tracer(Some(Tracer::new(buffer.clone())));
+        for _ in 0..3 {
+            cpu.step(&mut memory).unwrap();
+        }
+
+        let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let lines: Vec<_> = trace.lines().collect();
+        assert_eq!(lines, [
+            "A:01 F:80 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02",
+            "A:01 F:80 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0101 PCMEM:C3,13,02,00",
+            "A:01 F:80 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0213 PCMEM:00,00,00,00",
+        ]);
+    }
+}
@@ -0,0 +1,315 @@
+//! A disassembler for SM83 machine code.
+//!
+//! Immediate operands are written in hex (`$xx`, `$xxxx`), and the targets of
+//! relative jumps are resolved to absolute addresses. With labels, every jump
+//! or call target inside the disassembled code is labelled `Addr_XXXX`.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+use crate::cpu::instructions::metadata::{ METADATA, METADATA_PREFIX };
+use crate::memory::Memory;
+
+/// A disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// The address of the opcode.
+    pub addr: u16,
+    /// The encoded instruction.
+    pub bytes: Vec<u8>,
+    /// The assembly, such as `LD ($ff00+$47), A`.
+    pub text: String,
+    /// The address jumped to or called, if any.
+    pub target: Option<u16>,
+}
+
+/// Decodes the instruction at the start of `bytes`, located at `addr`.
+/// An illegal opcode is decoded as a single byte of data.
+/// Returns `None` if `bytes` is empty or ends before the instruction does.
+pub fn decode(bytes: &[u8], addr: u16) -> Option<Decoded> {
+    let opcode = *bytes.first()?;
+    let metadata = match opcode {
+        0xcb => &METADATA_PREFIX[*bytes.get(1)? as usize],
+        _ => &METADATA[opcode as usize],
+    };
+    let length = metadata.length as usize;
+    if bytes.len() < length {
+        return None;
+    }
+    if metadata.mnemonic == "ILLEGAL" {
+        return Some(data(opcode, addr));
+    }
+
+    let bytes = &bytes[..length];
+    let next = addr.wrapping_add(length as u16);
+    let mut target = None;
+    let mut text = metadata.mnemonic.replace("FF00", "$ff00");
+
+    if let Some(vector) = text.strip_prefix("RST ").and_then(|arg| arg.strip_suffix('h')) {
+        let vector = u16::from_str_radix(vector, 16).unwrap();
+        target = Some(vector);
+        text = format!("RST ${:02x}", vector);
+    } else if text.contains("u16") {
+        let word = u16::from_le_bytes([bytes[1], bytes[2]]);
+        if text.starts_with("JP") || text.starts_with("CALL") {
+            target = Some(word);
+        }
+        text = text.replace("u16", &format!("${:04x}", word));
+    } else if text.starts_with("JR") {
+        let word = next.wrapping_add(bytes[1] as i8 as u16);
+        target = Some(word);
+        text = text.replace("i8", &format!("${:04x}", word));
+    } else if text.contains("i8") {
+        let offset = bytes[1] as i8;
+        let sign = if offset < 0 { "-" } else { "+" };
+        let operand = format!("{}${:02x}", sign, offset.unsigned_abs());
+        text = text.replace("+i8", &operand).replace("i8", &operand);
+    } else if text.contains("u8") {
+        text = text.replace("u8", &format!("${:02x}", bytes[1]));
+    }
+
+    Some(Decoded { addr, bytes: bytes.to_vec(), text, target })
+}
+
+/// Returns `byte`, located at `addr`, as a single byte of data.
+fn data(byte: u8, addr: u16) -> Decoded {
+    Decoded { addr, bytes: vec![byte], text: format!(".DB ${:02x}", byte), target: None }
+}
+
+/// Disassembles `bytes`, the first of which is located at `origin`.
+/// An instruction cut short by the end of `bytes` is disassembled as data.
+pub fn disassemble(bytes: &[u8], origin: u16) -> Vec<Decoded> {
+    let mut decoded = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let addr = origin.wrapping_add(offset as u16);
+        let instruction = decode(&bytes[offset..], addr)
+            .unwrap_or_else(|| data(bytes[offset], addr));
+        offset += instruction.bytes.len();
+        decoded.push(instruction);
+    }
+    decoded
+}
+
+/// Disassembles the memory in `range`, which may end at 0xffff.
+pub fn disassemble_memory(memory: &Memory, range: RangeInclusive<u16>) -> Vec<Decoded> {
+    let bytes: Vec<u8> = range.clone().map(|addr| memory.read_byte(addr)).collect();
+    disassemble(&bytes, *range.start())
+}
+
+/// Formats `decoded` one instruction per line, annotated with its address and bytes.
+/// With `labels`, targets inside `decoded` are labelled and referred to by their label.
+pub fn format(decoded: &[Decoded], labels: bool) -> String {
+    let addrs: BTreeSet<u16> = decoded.iter().map(|instruction| instruction.addr).collect();
+    let labelled: BTreeSet<u16> = decoded.iter()
+        .filter_map(|instruction| instruction.target)
+        .filter(|target| labels && addrs.contains(target))
+        .collect();
+
+    let mut out = String::new();
+    for instruction in decoded {
+        if labelled.contains(&instruction.addr) {
+            writeln!(out, "Addr_{:04X}:", instruction.addr).unwrap();
+        }
+
+        let mut text = instruction.text.clone();
+        if let Some(target) = instruction.target.filter(|target| labelled.contains(target)) {
+            let operand = if text.starts_with("RST") {
+                format!("${:02x}", target)
+            } else {
+                format!("${:04x}", target)
+            };
+            text = text.replace(&operand, &format!("Addr_{:04X}", target));
+        }
+
+        let bytes: Vec<String> = instruction.bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        writeln!(out, "    {:<24}; ${:04x}  {}", text, instruction.addr, bytes.join(" ")).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::memory::BOOT_ROM;
+
+    /// The ALU instructions `boot_rom.z80` writes without their `A, ` operand.
+    const ALU: [&str; 8] = ["ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP"];
+
+    /// A line of `boot_rom.z80`.
+    enum Source {
+        Instruction { addr: u16, text: String },
+        Data { label: u16, bytes: Vec<u8> },
+    }
+
+    /// Parses the instructions and data of `boot_rom.z80`.
+    /// Returns them with the address of every label.
+    fn parse_source() -> (Vec<Source>, HashMap<String, u16>) {
+        let source = include_str!("../boot_rom.z80");
+        let mut lines = Vec::new();
+        let mut labels = HashMap::new();
+        let mut label = 0;
+
+        for line in source.lines() {
+            let (code, comment) = line.split_once(';').unwrap_or((line, ""));
+            let code = code.trim();
+            if let Some(name) = code.strip_suffix(':') {
+                label = u16::from_str_radix(name.trim_start_matches("Addr_"), 16).unwrap();
+                labels.insert(name.to_string(), label);
+            } else if let Some(data) = code.strip_prefix(".DB") {
+                let bytes = data.split(',')
+                    .map(|byte| u8::from_str_radix(byte.trim().trim_start_matches('$'), 16).unwrap())
+                    .collect();
+                lines.push(Source::Data { label, bytes });
+            } else if !code.is_empty() {
+                let addr = comment.trim().trim_start_matches('$');
+                let addr = u16::from_str_radix(&addr[..4], 16).unwrap();
+                lines.push(Source::Instruction { addr, text: code.to_string() });
+            }
+        }
+        (lines, labels)
+    }
+
+    /// Normalises `text`, located at `addr`, to compare the two syntaxes:
+    /// labels and raw relative offsets are resolved, and whitespace and case ignored.
+    fn normalise(text: &str, addr: u16, labels: &HashMap<String, u16>) -> String {
+        let mut text = text.to_lowercase().replace(char::is_whitespace, "");
+        for (name, label) in labels {
+            text = text.replace(&name.to_lowercase(), &format!("${:04x}", label));
+        }
+        if text.starts_with("jr") {
+            let (jump, operand) = text.rsplit_once('$').unwrap();
+            if operand.len() == 2 {
+                let offset = u8::from_str_radix(operand, 16).unwrap() as i8;
+                let target = addr.wrapping_add(2).wrapping_add(offset as u16);
+                text = format!("{}${:04x}", jump, target);
+            }
+        }
+        for alu in ALU {
+            let prefix = format!("{}a,", alu.to_lowercase());
+            if let Some(operand) = text.strip_prefix(&prefix) {
+                text = format!("{}{}", alu.to_lowercase(), operand);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn decodes_operands() {
+        let cases: [(&[u8], &str, Option<u16>); 9] = [
+            (&[0x31, 0xfe, 0xff], "LD SP, $fffe", None),
+            (&[0x20, 0xfb], "JR NZ, $00fd", Some(0x00fd)),
+            (&[0xe0, 0x47], "LD ($ff00+$47), A", None),
+            (&[0xe2], "LD ($ff00+C), A", None),
+            (&[0xf8, 0xfe], "LD HL, SP-$02", None),
+            (&[0xe8, 0x05], "ADD SP, +$05", None),
+            (&[0xcd, 0x95, 0x00], "CALL $0095", Some(0x0095)),
+            (&[0xcb, 0x7c], "BIT 7, H", None),
+            (&[0xff], "RST $38", Some(0x0038)),
+        ];
+        for (bytes, text, target) in cases {
+            let decoded = decode(bytes, 0x0100).unwrap();
+            assert_eq!(decoded.text, text);
+            assert_eq!(decoded.target, target);
+            assert_eq!(decoded.bytes, bytes);
+        }
+    }
+
+    #[test]
+    fn decodes_illegal_instructions_as_data() {
+        assert_eq!(decode(&[0xd3], 0).unwrap().text, ".DB $d3");
+    }
+
+    #[test]
+    fn decodes_nothing_from_short_input() {
+        assert_eq!(decode(&[], 0), None);
+        assert_eq!(decode(&[0x01, 0x00], 0), None);
+        assert_eq!(decode(&[0xcb], 0), None);
+    }
+
+    #[test]
+    fn disassembles_truncated_instructions_as_data() {
+        let text: Vec<_> = disassemble(&[0x00, 0x01, 0x00], 0)
+            .into_iter()
+            .map(|instruction| instruction.text)
+            .collect();
+
+        assert_eq!(text, ["NOP", ".DB $01", "NOP"]);
+        assert!(disassemble(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn formats_labels_for_targets_inside_the_code() {
+        // LD B, $02; DEC B; JR NZ, -3; CALL $1234
+        let decoded = disassemble(&[0x06, 0x02, 0x05, 0x20, 0xfd, 0xcd, 0x34, 0x12], 0x0200);
+
+        assert_eq!(format(&decoded, true), concat!(
+            "    LD B, $02               ; $0200  06 02\n",
+            "Addr_0202:\n",
+            "    DEC B                   ; $0202  05\n",
+            "    JR NZ, Addr_0202        ; $0203  20 fd\n",
+            "    CALL $1234              ; $0205  cd 34 12\n",
+        ));
+        assert!(format(&decoded, false).contains("JR NZ, $0202"));
+    }
+
+    #[test]
+    fn disassembles_a_memory_range() {
+        let mut memory = Memory::new();
+        memory.init();
+
+        let text: Vec<_> = disassemble_memory(&memory, 0x0000..=0x0006)
+            .into_iter()
+            .map(|instruction| instruction.text)
+            .collect();
+
+        assert_eq!(text, ["LD SP, $fffe", "XOR A, A", "LD HL, $9fff"]);
+    }
+
+    #[test]
+    fn disassembles_the_whole_address_space() {
+        let mut memory = Memory::new();
+        memory.write_byte(0xffff, 0x3c); // INC A
+
+        let decoded = disassemble_memory(&memory, 0x0000..=0xffff);
+
+        let length: usize = decoded.iter().map(|instruction| instruction.bytes.len()).sum();
+        assert_eq!(length, 0x10000);
+        let last = decoded.last().unwrap();
+        assert_eq!((last.addr, last.text.as_str()), (0xffff, "INC A"));
+    }
+
+    #[test]
+    fn round_trips_the_boot_rom() {
+        let (source, labels) = parse_source();
+
+        let mut next = 0x0000;
+        for line in source {
+            match line {
+                Source::Instruction { addr, text } => {
+                    assert_eq!(addr, next, "{}", text);
+                    let instruction = decode(&BOOT_ROM[addr as usize..], addr).unwrap();
+                    assert_eq!(
+                        normalise(&instruction.text, addr, &labels),
+                        normalise(&text, addr, &labels),
+                        "at {:#06x}", addr,
+                    );
+                    next += instruction.bytes.len() as u16;
+                }
+                Source::Data { label, bytes } => {
+                    // Data is only aligned with the disassembly at its label.
+                    if label >= next {
+                        next = label;
+                    }
+                    let start = next as usize;
+                    assert_eq!(&BOOT_ROM[start..start + bytes.len()], &bytes[..]);
+                    next += bytes.len() as u16;
+                }
+            }
+        }
+        assert_eq!(next, 0x0100);
+    }
+}
@@ -0,0 +1,154 @@
+//! The interrupt controller: the Interrupt Flag (IF) and Interrupt Enable (IE) registers.
+//!
+//! Components request interrupts by setting their bit in IF. An interrupt is
+//! pending while it is both requested and enabled in IE, and the CPU services
+//! the pending interrupt with the lowest bit first.
+
+/// The address of the Interrupt Flag register (IF), holding the interrupt requests.
+pub const IF: u16 = 0xff0f;
+
+/// The address of the Interrupt Enable register (IE).
+pub const IE: u16 = 0xffff;
+
+/// The interrupts, from the highest priority to the lowest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    /// Every interrupt, from the highest priority to the lowest.
+    pub const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank, Interrupt::LcdStat, Interrupt::Timer, Interrupt::Serial, Interrupt::Joypad,
+    ];
+
+    /// Returns the mask of the interrupt's bit in IF and IE.
+    pub fn mask(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Returns the address of the interrupt's handler.
+    pub fn vector(self) -> u16 {
+        0x40 + 8 * self as u16
+    }
+}
+
+#[derive(Default)]
+pub struct InterruptController {
+    /// IF, of which only the lower 5 bits exist.
+    requested: u8,
+    /// IE, of which all 8 bits can be written and read back.
+    enabled: u8,
+}
+
+impl InterruptController {
+    /// Returns a new instance of `InterruptController`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests `interrupt` by setting its bit in IF.
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.requested |= interrupt.mask();
+    }
+
+    /// Acknowledges `interrupt` by resetting its bit in IF, as it is serviced.
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.requested &= !interrupt.mask();
+    }
+
+    /// Returns whether `interrupt` is requested in IF, whether it is enabled or not.
+    pub fn requested(&self, interrupt: Interrupt) -> bool {
+        self.requested & interrupt.mask() != 0
+    }
+
+    /// Returns whether any interrupt is enabled in IE, of which only the lower 5 bits
+    /// are interrupts.
+    pub fn any_enabled(&self) -> bool {
+        Interrupt::ALL.iter().any(|interrupt| self.enabled & interrupt.mask() != 0)
+    }
+
+    /// Returns the highest priority interrupt that is both requested and enabled, if any.
+    pub fn pending(&self) -> Option<Interrupt> {
+        let pending = self.requested & self.enabled;
+        Interrupt::ALL.iter().copied().find(|interrupt| pending & interrupt.mask() != 0)
+    }
+
+    /// Returns the register at `addr`. The upper 3 bits of IF always read as 1.
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            IF => self.requested | 0xe0,
+            IE => self.enabled,
+            _ => unreachable!("{:#06x} is not an interrupt register", addr),
+        }
+    }
+
+    /// Writes the register at `addr`.
+    pub fn write_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            IF => self.requested = byte & 0x1f,
+            IE => self.enabled = byte,
+            _ => unreachable!("{:#06x} is not an interrupt register", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interrupts_map_to_their_bits_and_handlers() {
+        let vectors: Vec<_> = Interrupt::ALL.iter().map(|interrupt| interrupt.vector()).collect();
+        assert_eq!(vectors, [0x40, 0x48, 0x50, 0x58, 0x60]);
+        assert_eq!(Interrupt::Timer.mask(), 0b00100);
+        assert_eq!(Interrupt::Joypad.mask(), 0b10000);
+    }
+
+    #[test]
+    fn only_requested_and_enabled_interrupts_are_pending() {
+        let mut interrupts = InterruptController::new();
+        interrupts.request(Interrupt::Serial);
+        assert_eq!(interrupts.pending(), None);
+
+        interrupts.write_byte(IE, 0x1f);
+        assert_eq!(interrupts.pending(), Some(Interrupt::Serial));
+
+        interrupts.request(Interrupt::LcdStat);
+        assert_eq!(interrupts.pending(), Some(Interrupt::LcdStat));
+
+        interrupts.acknowledge(Interrupt::LcdStat);
+        assert_eq!(interrupts.pending(), Some(Interrupt::Serial));
+    }
+
+    #[test]
+    fn the_upper_bits_of_if_read_as_set() {
+        let mut interrupts = InterruptController::new();
+        assert_eq!(interrupts.read_byte(IF), 0xe0);
+
+        interrupts.write_byte(IF, 0x04);
+        interrupts.write_byte(IE, 0xff);
+        assert_eq!(interrupts.read_byte(IF), 0xe4);
+        assert_eq!(interrupts.read_byte(IE), 0xff);
+
+        interrupts.write_byte(IF, 0xff);
+        assert_eq!(interrupts.read_byte(IF), 0xff);
+        assert_eq!(interrupts.pending(), Some(Interrupt::VBlank));
+    }
+
+    #[test]
+    fn only_the_lower_bits_of_ie_enable_interrupts() {
+        let mut interrupts = InterruptController::new();
+        assert!(!interrupts.any_enabled());
+
+        interrupts.write_byte(IE, 0xe0);
+        assert!(!interrupts.any_enabled());
+
+        interrupts.write_byte(IE, Interrupt::Joypad.mask());
+        assert!(interrupts.any_enabled());
+    }
+}
@@ -0,0 +1,129 @@
+//! The joypad: the Joypad register (P1/JOYP).
+//!
+//! The eight buttons are wired as two groups of four lines, the direction keys
+//! and the action buttons, selected by writing 0 to bit 4 or 5 of P1. The lower
+//! nibble reads the selected lines, where a held button pulls its line low.
+
+use crate::interrupts::{ Interrupt, InterruptController };
+
+/// The address of the Joypad register (P1/JOYP).
+pub const P1: u16 = 0xff00;
+
+/// The buttons, in the order of their lines in P1 within their group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+impl Button {
+    /// Returns the mask of the button in the state of all eight buttons,
+    /// with the direction keys in the lower nibble.
+    fn mask(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[derive(Default)]
+pub struct Joypad {
+    /// Bits 4-5 of P1, selecting the action buttons and the direction keys when reset.
+    select: u8,
+    /// The held buttons, as given by `Button::mask`.
+    held: u8,
+}
+
+impl Joypad {
+    /// Returns a new instance of `Joypad`, with no button held.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the lines selected in P1 that are low, as a held button pulls them.
+    fn low_lines(&self) -> u8 {
+        let mut lines = 0;
+        if self.select & 0x10 == 0 {
+            lines |= self.held & 0x0f;
+        }
+        if self.select & 0x20 == 0 {
+            lines |= self.held >> 4;
+        }
+        lines
+    }
+
+    /// Returns whether a button on a selected line is held, which wakes the CPU from STOP.
+    pub fn any_selected_held(&self) -> bool {
+        self.low_lines() != 0
+    }
+
+    /// Holds `button`, requesting the joypad interrupt if this pulls a selected line low.
+    pub fn press(&mut self, button: Button, interrupts: &mut InterruptController) {
+        let low_lines = self.low_lines();
+        self.held |= button.mask();
+        if self.low_lines() & !low_lines != 0 {
+            interrupts.request(Interrupt::Joypad);
+        }
+    }
+
+    /// Lets go of `button`.
+    pub fn release(&mut self, button: Button) {
+        self.held &= !button.mask();
+    }
+
+    /// Returns P1: bits 6-7 read as 1, and the lower nibble as 0 for the selected
+    /// lines of held buttons.
+    pub fn read_byte(&self) -> u8 {
+        0xc0 | self.select | (!self.low_lines() & 0x0f)
+    }
+
+    /// Writes P1, of which only the selection bits 4-5 can be written.
+    pub fn write_byte(&mut self, byte: u8) {
+        self.select = byte & 0x30;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupts::IE;
+
+    #[test]
+    fn only_the_selected_lines_read_held_buttons() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = InterruptController::new();
+        joypad.press(Button::Start, &mut interrupts);
+        joypad.press(Button::Left, &mut interrupts);
+
+        joypad.write_byte(0x30);
+        assert_eq!(joypad.read_byte(), 0xff);
+        assert!(!joypad.any_selected_held());
+
+        joypad.write_byte(0x20);
+        assert_eq!(joypad.read_byte(), 0xed);
+        joypad.write_byte(0x10);
+        assert_eq!(joypad.read_byte(), 0xd7);
+        assert!(joypad.any_selected_held());
+
+        joypad.release(Button::Start);
+        assert_eq!(joypad.read_byte(), 0xdf);
+    }
+
+    #[test]
+    fn pressing_a_selected_button_requests_an_interrupt() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = InterruptController::new();
+        interrupts.write_byte(IE, Interrupt::Joypad.mask());
+
+        joypad.write_byte(0x20);
+        joypad.press(Button::A, &mut interrupts);
+        assert_eq!(interrupts.pending(), None);
+
+        joypad.press(Button::Down, &mut interrupts);
+        assert_eq!(interrupts.pending(), Some(Interrupt::Joypad));
+    }
+}
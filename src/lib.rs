@@ -1,2 +1,10 @@
+pub mod asm;
 pub mod cpu;
+pub mod disasm;
+pub mod interrupts;
+pub mod joypad;
 pub mod memory;
+pub mod model;
+pub mod serial;
+pub mod speed;
+pub mod timer;
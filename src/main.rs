@@ -1,12 +1,52 @@
+use std::env;
+use std::fs::{ self, File };
+use std::io::BufWriter;
+
 use disco_gb::cpu::Cpu;
+use disco_gb::cpu::trace::Tracer;
 use disco_gb::memory::Memory;
+use disco_gb::model::Model;
 
+/// Usage: disco_gb [ROM] [--skip-boot] [--trace FILE]
 fn main() {
+    let mut args = env::args().skip(1);
+    let mut rom_path = None;
+    let mut skip_boot = false;
+    let mut trace_path = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--skip-boot" => skip_boot = true,
+            "--trace" => trace_path = args.next(),
+            _ => rom_path = Some(arg),
+        }
+    }
+
     let mut cpu = Cpu::new();
     let mut memory = Memory::new();
 
-    memory.init();
+    if let Some(path) = rom_path {
+        let rom = fs::read(path).expect("Failed to read the ROM.");
+        memory.load_rom(&rom);
+    }
 
-    cpu.run(&mut memory);
-}
+    if skip_boot {
+        memory.skip_boot(Model::Dmg);
+        cpu.skip_boot(Model::Dmg, &memory);
+    } else {
+        memory.init();
+    }
 
+    if let Some(path) = trace_path {
+        let file = File::create(path).expect("Failed to create the trace file.");
+        cpu.set_tracer(Some(Tracer::new(BufWriter::new(file))));
+        // Gameboy Doctor expects LY to always read 0x90, as there is no PPU yet.
+        memory.write_byte(0xff44, 0x90);
+    }
+
+    if let Err(err) = cpu.run(&mut memory) {
+        eprintln!("{}", err);
+        // Flushes the trace.
+        drop(cpu);
+        std::process::exit(1);
+    }
+}
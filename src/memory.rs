@@ -1,9 +1,59 @@
 use std::fs::File;
 use std::io::prelude::*;
 
-const MEMORY_SIZE: u32 = 0x10000; // 0xFFFF + 0x1;
+use crate::interrupts::{ self, Interrupt, InterruptController };
+use crate::joypad::{ Button, Joypad, P1 };
+use crate::model::Model;
+use crate::serial::{ self, Serial };
+use crate::speed::{ KEY1, SpeedSwitch };
+use crate::timer::{ self, Timer };
 
-const BOOT_ROM: [u8; 0x100] = [
+const MEMORY_SIZE: usize = 0x10000; // 0xFFFF + 0x1;
+
+/// The size of the cartridge ROM mapped without a memory bank controller.
+const ROM_SIZE: usize = 0x8000;
+
+/// The IO registers as the boot ROM leaves them, shared by every model.
+/// Those that differ between models are given by `Model::post_boot_io`.
+const POST_BOOT_IO: [(u16, u8); 31] = [
+    (0xff00, 0xcf), // P1
+    (0xff01, 0x00), // SB
+    (0xff05, 0x00), // TIMA
+    (0xff06, 0x00), // TMA
+    (0xff07, 0xf8), // TAC
+    (0xff0f, 0xe1), // IF
+    (0xff10, 0x80), // NR10
+    (0xff11, 0xbf), // NR11
+    (0xff12, 0xf3), // NR12
+    (0xff13, 0xff), // NR13
+    (0xff14, 0xbf), // NR14
+    (0xff16, 0x3f), // NR21
+    (0xff17, 0x00), // NR22
+    (0xff18, 0xff), // NR23
+    (0xff19, 0xbf), // NR24
+    (0xff1a, 0x7f), // NR30
+    (0xff1b, 0xff), // NR31
+    (0xff1c, 0x9f), // NR32
+    (0xff1d, 0xff), // NR33
+    (0xff1e, 0xbf), // NR34
+    (0xff20, 0xff), // NR41
+    (0xff21, 0x00), // NR42
+    (0xff22, 0x00), // NR43
+    (0xff23, 0xbf), // NR44
+    (0xff24, 0x77), // NR50
+    (0xff25, 0xf3), // NR51
+    (0xff40, 0x91), // LCDC
+    (0xff47, 0xfc), // BGP
+    (0xff4a, 0x00), // WY
+    (0xff50, 0x01), // Boot ROM disabled
+    (0xffff, 0x00), // IE
+];
+
+/// The address of the register unmapping the boot ROM once written.
+const BOOT: u16 = 0xff50;
+
+/// The DMG boot ROM, see `boot_rom.z80`.
+pub const BOOT_ROM: [u8; 0x100] = [
     0x31, 0xfe, 0xff, 0xaf, 0x21, 0xff, 0x9f, 0x32, 0xcb, 0x7c, 0x20, 0xfb, 0x21, 0x26, 0xff, 0x0e,
     0x11, 0x3e, 0x80, 0x32, 0xe2, 0x0c, 0x3e, 0xf3, 0xe2, 0x32, 0x3e, 0x77, 0x77, 0x3e, 0xfc, 0xe0,
     0x47, 0x11, 0x04, 0x01, 0x21, 0x10, 0x80, 0x1a, 0xcd, 0x95, 0x00, 0xcd, 0x96, 0x00, 0x13, 0x7b,
@@ -23,28 +73,161 @@ const BOOT_ROM: [u8; 0x100] = [
 ];
 
 pub struct Memory {
-    mem_map: [u8;0x10000], // 0x10000 = 0xFFFF+0x1
+    mem_map: [u8; MEMORY_SIZE],
+    joypad: Joypad,
+    timer: Timer,
+    serial: Serial,
+    interrupts: InterruptController,
+    speed: SpeedSwitch,
+    /// The T-cycles elapsed at normal speed, which drive the PPU and APU
+    /// whatever the speed of the CPU.
+    dots: u64,
+    /// Whether every address is plain RAM, without IO registers.
+    flat: bool,
+    /// Whether the boot ROM is mapped over the first 0x100 bytes of the cartridge.
+    boot_rom_mapped: bool,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Memory {
     pub fn new() -> Self {
         Self {
-            mem_map: [0;0x10000], // 0x10000 = 0xFFFF+0x1
+            mem_map: [0; MEMORY_SIZE],
+            joypad: Joypad::new(),
+            timer: Timer::new(),
+            serial: Serial::new(),
+            interrupts: InterruptController::new(),
+            speed: SpeedSwitch::new(),
+            dots: 0,
+            flat: false,
+            boot_rom_mapped: false,
         }
     }
 
+    /// Returns memory where every address is plain RAM, nothing is clocked and no
+    /// interrupt is ever requested, the flat bus the SingleStepTests JSON test
+    /// vectors are recorded on.
+    pub fn flat() -> Self {
+        Self { flat: true, ..Self::new() }
+    }
+
+    /// Maps the boot ROM over the first 0x100 bytes of the cartridge,
+    /// until it unmaps itself by writing to 0xff50.
     pub fn init(&mut self) {
-        for (i, v) in BOOT_ROM.iter().enumerate() {
-            self.mem_map[i] = *v;
+        self.boot_rom_mapped = true;
+    }
+
+    /// Maps the cartridge `rom`, which must fit without a memory bank controller.
+    /// Call `init` to run the boot ROM, or `skip_boot` instead.
+    pub fn load_rom(&mut self, rom: &[u8]) {
+        let len = rom.len().min(ROM_SIZE);
+        self.mem_map[..len].copy_from_slice(&rom[..len]);
+    }
+
+    /// Sets the IO registers to the values the boot ROM of `model` leaves them with.
+    /// Only the CGB can switch to double speed.
+    pub fn skip_boot(&mut self, model: Model) {
+        self.speed.set_cgb(model == Model::Cgb);
+        for &(addr, byte) in POST_BOOT_IO.iter().chain(model.post_boot_io()) {
+            self.write_byte(addr, byte);
+        }
+        self.timer.set_counter(model.post_boot_timer_counter());
+    }
+
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x00ff if self.boot_rom_mapped => BOOT_ROM[addr as usize],
+            P1 if !self.flat => self.joypad.read_byte(),
+            serial::SB..=serial::SC if !self.flat => self.serial.read_byte(addr),
+            timer::DIV..=timer::TAC if !self.flat => self.timer.read_byte(addr),
+            interrupts::IF | interrupts::IE if !self.flat => self.interrupts.read_byte(addr),
+            KEY1 if !self.flat => self.speed.read_byte(),
+            _ => self.mem_map[addr as usize],
+        }
+    }
+
+    pub fn write_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            P1 if !self.flat => self.joypad.write_byte(byte),
+            serial::SB..=serial::SC if !self.flat => self.serial.write_byte(addr, byte),
+            timer::DIV..=timer::TAC if !self.flat => {
+                self.timer.write_byte(addr, byte, &mut self.interrupts)
+            }
+            interrupts::IF | interrupts::IE if !self.flat => self.interrupts.write_byte(addr, byte),
+            KEY1 if !self.flat => self.speed.write_byte(byte),
+            BOOT if !self.flat => {
+                // Once unmapped, the boot ROM stays unmapped until reset.
+                if byte & 0x01 != 0 {
+                    self.boot_rom_mapped = false;
+                }
+                self.mem_map[addr as usize] = byte;
+            }
+            _ => self.mem_map[addr as usize] = byte,
+        }
+    }
+
+    /// Advances every component driven by the clock by `cycles` T-cycles of the CPU.
+    /// The timer and serial port run at the speed of the CPU, while only half
+    /// as many dots elapse in double speed.
+    pub fn tick(&mut self, cycles: u8) {
+        if self.flat {
+            return;
         }
+        self.timer.tick(cycles, &mut self.interrupts);
+        self.serial.tick(cycles, &mut self.interrupts);
+        self.dots += if self.speed.double_speed() { cycles / 2 } else { cycles } as u64;
     }
 
-    pub fn read_byte(&self, addr: usize) -> u8 {
-        self.mem_map[addr]
+    /// Returns the T-cycles elapsed at normal speed, whatever the speed of the CPU.
+    pub fn dots(&self) -> u64 {
+        self.dots
     }
 
-    pub fn write_byte(&mut self, addr: usize, byte: u8) {
-        self.mem_map[addr] = byte;
+    /// Returns whether the CPU runs at double speed.
+    pub fn double_speed(&self) -> bool {
+        self.speed.double_speed()
+    }
+
+    /// Toggles the speed if the switch is armed in KEY1, as STOP does.
+    /// Returns whether it did.
+    pub fn switch_speed(&mut self) -> bool {
+        self.speed.switch()
+    }
+
+    /// Holds `button`, requesting the joypad interrupt if its line is selected in P1.
+    pub fn press_button(&mut self, button: Button) {
+        self.joypad.press(button, &mut self.interrupts);
+    }
+
+    /// Lets go of `button`.
+    pub fn release_button(&mut self, button: Button) {
+        self.joypad.release(button);
+    }
+
+    /// Returns whether the joypad wakes the CPU from STOP: a button is held on a
+    /// line selected in P1, or the joypad interrupt is requested.
+    pub fn joypad_wakes(&self) -> bool {
+        self.joypad.any_selected_held() || self.interrupts.requested(Interrupt::Joypad)
+    }
+
+    /// Returns every byte sent over the serial port so far.
+    pub fn serial_output(&self) -> &[u8] {
+        self.serial.output()
+    }
+
+    /// Returns the interrupt controller.
+    pub fn interrupts(&self) -> &InterruptController {
+        &self.interrupts
+    }
+
+    /// Returns the interrupt controller, through which components request interrupts.
+    pub fn interrupts_mut(&mut self) -> &mut InterruptController {
+        &mut self.interrupts
     }
 
     pub fn file_dump(&self) {
@@ -53,3 +236,45 @@ impl Memory {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_boot_rom_is_mapped_over_the_cartridge_until_unmapped() {
+        let mut memory = Memory::new();
+        let mut rom = [0x00; 0x150];
+        rom[0x40] = 0xd9; // RETI
+        memory.load_rom(&rom);
+        memory.init();
+
+        assert_eq!(memory.read_byte(0x0000), BOOT_ROM[0]);
+        assert_eq!(memory.read_byte(0x0040), BOOT_ROM[0x40]);
+        assert_eq!(memory.read_byte(0x0100), 0x00);
+
+        memory.write_byte(BOOT, 0x01);
+        assert_eq!(memory.read_byte(0x0040), 0xd9);
+    }
+
+    #[test]
+    fn skip_boot_sets_the_io_registers_of_each_model() {
+        // (model, SC, NR52, STAT, DMA)
+        let models = [
+            (Model::Dmg0, 0x7e, 0xf1, 0x81, 0xff),
+            (Model::Dmg, 0x7e, 0xf1, 0x85, 0xff),
+            (Model::Sgb, 0x7e, 0xf0, 0x85, 0xff),
+            (Model::Cgb, 0x7f, 0xf1, 0x85, 0x00),
+        ];
+        for (model, sc, nr52, stat, dma) in models {
+            let mut memory = Memory::new();
+            memory.skip_boot(model);
+
+            let io: Vec<_> = [0xff02, 0xff26, 0xff41, 0xff46].iter()
+                .map(|&addr| memory.read_byte(addr))
+                .collect();
+            assert_eq!(io, [sc, nr52, stat, dma], "{:?}", model);
+            assert_eq!(memory.read_byte(0xff00), 0xcf, "{:?}", model);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! The Game Boy models and the state their boot ROMs leave behind.
+
+/// The Game Boy models, which differ in the register values left by the boot ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// The original Game Boy with its early boot ROM.
+    Dmg0,
+    /// The original Game Boy.
+    Dmg,
+    /// The Game Boy Pocket.
+    Mgb,
+    /// The Super Game Boy.
+    Sgb,
+    /// The Super Game Boy 2.
+    Sgb2,
+    /// The Game Boy Color, running a Color game.
+    Cgb,
+}
+
+impl Model {
+    /// Returns the AF, BC, DE and HL registers at 0x0100, once the boot ROM is done.
+    /// `header_checksum` is the byte at 0x014d of the cartridge, on which the
+    /// H and C flags depend on the DMG and MGB: they are set unless it is zero.
+    pub fn post_boot_registers(self, header_checksum: u8) -> [u16; 4] {
+        let checksum_flags = if header_checksum == 0 { 0x80 } else { 0xb0 };
+        match self {
+            Model::Dmg0 => [0x0100, 0xff13, 0x00c1, 0x8403],
+            Model::Dmg => [0x0100 | checksum_flags, 0x0013, 0x00d8, 0x014d],
+            Model::Mgb => [0xff00 | checksum_flags, 0x0013, 0x00d8, 0x014d],
+            Model::Sgb => [0x0100, 0x0014, 0x0000, 0xc060],
+            Model::Sgb2 => [0xff00, 0x0014, 0x0000, 0xc060],
+            Model::Cgb => [0x1180, 0x0000, 0xff56, 0x000d],
+        }
+    }
+
+    /// Returns the IO registers whose values at 0x0100 differ between models: SC,
+    /// NR52, STAT and DMA, and the VRAM and WRAM banks of the CGB. Where the values
+    /// of the SGB and CGB are undocumented, such as their STAT, the DMG values are used.
+    pub fn post_boot_io(self) -> &'static [(u16, u8)] {
+        match self {
+            Model::Dmg0 => &[(0xff02, 0x7e), (0xff26, 0xf1), (0xff41, 0x81), (0xff46, 0xff)],
+            Model::Dmg | Model::Mgb => {
+                &[(0xff02, 0x7e), (0xff26, 0xf1), (0xff41, 0x85), (0xff46, 0xff)]
+            }
+            Model::Sgb | Model::Sgb2 => {
+                &[(0xff02, 0x7e), (0xff26, 0xf0), (0xff41, 0x85), (0xff46, 0xff)]
+            }
+            Model::Cgb => &[
+                (0xff02, 0x7f), (0xff26, 0xf1), (0xff41, 0x85), (0xff46, 0x00),
+                (0xff4f, 0xfe), (0xff70, 0xf8),
+            ],
+        }
+    }
+
+    /// Returns the internal counter of the timer at 0x0100, whose upper byte is DIV.
+    /// It is undocumented for the SGB and CGB, so it is left at zero for them.
+    pub fn post_boot_timer_counter(self) -> u16 {
+        match self {
+            Model::Dmg0 => 0x1800,
+            Model::Dmg | Model::Mgb => 0xabcc,
+            Model::Sgb | Model::Sgb2 | Model::Cgb => 0x0000,
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! The serial port: the Serial Transfer Data (SB) and Serial Transfer Control (SC).
+//!
+//! There is no link cable, so a transfer shifts out SB and shifts in 0xff.
+//! Every byte sent is captured, as test ROMs report their results over serial.
+
+use crate::interrupts::{ Interrupt, InterruptController };
+
+/// The address of the Serial Transfer Data (SB).
+pub const SB: u16 = 0xff01;
+
+/// The address of the Serial Transfer Control (SC).
+pub const SC: u16 = 0xff02;
+
+/// The T-cycles a transfer on the internal clock takes: 8 bits at 8192 Hz.
+const TRANSFER_CYCLES: u16 = 8 * 512;
+
+#[derive(Default)]
+pub struct Serial {
+    sb: u8,
+    sc: u8,
+    /// The T-cycles left in the transfer, zero if there is none.
+    remaining: u16,
+    output: Vec<u8>,
+}
+
+impl Serial {
+    /// Returns a new instance of `Serial`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns every byte sent so far.
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Advances the transfer by `cycles` T-cycles.
+    /// Requests the serial interrupt if it completes.
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut InterruptController) {
+        if self.remaining == 0 {
+            return;
+        }
+        self.remaining = self.remaining.saturating_sub(cycles as u16);
+        if self.remaining == 0 {
+            self.sb = 0xff;
+            self.sc &= 0x7f;
+            interrupts.request(Interrupt::Serial);
+        }
+    }
+
+    /// Returns the register at `addr`.
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            SB => self.sb,
+            SC => self.sc | 0x7e,
+            _ => unreachable!("{:#06x} is not a serial register", addr),
+        }
+    }
+
+    /// Writes the register at `addr`.
+    /// Setting both the transfer start and internal clock bits of SC sends SB.
+    pub fn write_byte(&mut self, addr: u16, byte: u8) {
+        match addr {
+            SB => self.sb = byte,
+            SC => {
+                self.sc = byte & 0x81;
+                if self.sc == 0x81 {
+                    self.output.push(self.sb);
+                    self.remaining = TRANSFER_CYCLES;
+                }
+            }
+            _ => unreachable!("{:#06x} is not a serial register", addr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupts::IE;
+
+    #[test]
+    fn a_transfer_on_the_internal_clock_sends_sb() {
+        let mut serial = Serial::new();
+        serial.write_byte(SB, b'P');
+        serial.write_byte(SC, 0x81);
+
+        assert_eq!(serial.output(), b"P");
+        assert_eq!(serial.read_byte(SC), 0xff);
+    }
+
+    #[test]
+    fn a_transfer_completes_after_8_bits_and_requests_an_interrupt() {
+        let mut serial = Serial::new();
+        serial.write_byte(SB, 0x42);
+        serial.write_byte(SC, 0x81);
+
+        let mut interrupts = InterruptController::new();
+        interrupts.write_byte(IE, Interrupt::Serial.mask());
+
+        for _ in 0..TRANSFER_CYCLES / 4 - 1 {
+            serial.tick(4, &mut interrupts);
+        }
+        assert_eq!(interrupts.pending(), None);
+        serial.tick(4, &mut interrupts);
+        assert_eq!(interrupts.pending(), Some(Interrupt::Serial));
+        assert_eq!(serial.read_byte(SB), 0xff);
+        assert_eq!(serial.read_byte(SC), 0x7f);
+    }
+
+    #[test]
+    fn nothing_is_sent_on_the_external_clock() {
+        let mut serial = Serial::new();
+        serial.write_byte(SB, 0x42);
+        serial.write_byte(SC, 0x80);
+
+        let mut interrupts = InterruptController::new();
+        interrupts.write_byte(IE, Interrupt::Serial.mask());
+        serial.tick(255, &mut interrupts);
+
+        assert!(serial.output().is_empty());
+        assert_eq!(interrupts.pending(), None);
+        assert_eq!(serial.read_byte(SC), 0xfe);
+    }
+}
@@ -0,0 +1,94 @@
+//! The CGB speed switch: the Prepare Speed Switch register (KEY1).
+//!
+//! A CGB game arms the switch by setting bit 0 of KEY1, then executes STOP to
+//! toggle between normal and double speed. In double speed, the CPU, the timer
+//! and the serial port run twice as fast, while the PPU and APU keep their timing.
+
+/// The address of the Prepare Speed Switch register (KEY1).
+pub const KEY1: u16 = 0xff4d;
+
+#[derive(Default)]
+pub struct SpeedSwitch {
+    /// Whether KEY1 exists, only on the CGB.
+    cgb: bool,
+    double_speed: bool,
+    armed: bool,
+}
+
+impl SpeedSwitch {
+    /// Returns a new instance of `SpeedSwitch`, at normal speed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the speed can be switched, which is only the case on the CGB.
+    pub fn set_cgb(&mut self, cgb: bool) {
+        self.cgb = cgb;
+    }
+
+    /// Returns whether the CPU runs at double speed.
+    pub fn double_speed(&self) -> bool {
+        self.double_speed
+    }
+
+    /// Toggles the speed if the switch is armed, as STOP does.
+    /// Returns whether it did.
+    pub fn switch(&mut self) -> bool {
+        if !self.armed {
+            return false;
+        }
+        self.armed = false;
+        self.double_speed = !self.double_speed;
+        true
+    }
+
+    /// Returns KEY1: the current speed in bit 7 and the armed switch in bit 0.
+    /// It reads as 0xff without a CGB.
+    pub fn read_byte(&self) -> u8 {
+        if !self.cgb {
+            return 0xff;
+        }
+        0x7e | (self.double_speed as u8) << 7 | self.armed as u8
+    }
+
+    /// Writes KEY1, of which only bit 0 can be written.
+    pub fn write_byte(&mut self, byte: u8) {
+        if self.cgb {
+            self.armed = byte & 0x01 != 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_armed_switch_toggles_the_speed() {
+        let mut speed = SpeedSwitch::new();
+        speed.set_cgb(true);
+        assert_eq!(speed.read_byte(), 0x7e);
+        assert!(!speed.switch());
+
+        speed.write_byte(0x01);
+        assert_eq!(speed.read_byte(), 0x7f);
+        assert!(speed.switch());
+        assert!(speed.double_speed());
+        assert_eq!(speed.read_byte(), 0xfe);
+
+        speed.write_byte(0x01);
+        assert!(speed.switch());
+        assert!(!speed.double_speed());
+        assert_eq!(speed.read_byte(), 0x7e);
+    }
+
+    #[test]
+    fn the_speed_never_switches_without_a_cgb() {
+        let mut speed = SpeedSwitch::new();
+        speed.write_byte(0x01);
+
+        assert_eq!(speed.read_byte(), 0xff);
+        assert!(!speed.switch());
+        assert!(!speed.double_speed());
+    }
+}
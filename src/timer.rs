@@ -0,0 +1,170 @@
+//! The timer: the Divider Register (DIV) and the programmable TIMA, TMA and TAC.
+//!
+//! DIV is the upper byte of a 16 bit counter incremented every T-cycle.
+//! TIMA is incremented on the falling edge of the counter bit selected by TAC,
+//! so writing to DIV or TAC can increment it too.
+
+use crate::interrupts::{ Interrupt, InterruptController };
+
+/// The address of the Divider Register (DIV).
+pub const DIV: u16 = 0xff04;
+
+/// The address of the Timer Counter (TIMA).
+pub const TIMA: u16 = 0xff05;
+
+/// The address of the Timer Modulo (TMA), reloaded into TIMA when it overflows.
+pub const TMA: u16 = 0xff06;
+
+/// The address of the Timer Control (TAC).
+pub const TAC: u16 = 0xff07;
+
+/// The counter bit driving TIMA, indexed by the clock select bits of TAC.
+const TAC_BITS: [u16; 4] = [9, 3, 5, 7];
+
+#[derive(Default)]
+pub struct Timer {
+    counter: u16,
+    tima: u8,
+    tma: u8,
+    tac: u8,
+}
+
+impl Timer {
+    /// Returns a new instance of `Timer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the internal counter, as left by the boot ROM.
+    pub fn set_counter(&mut self, counter: u16) {
+        self.counter = counter;
+    }
+
+    /// Advances the timer by `cycles` T-cycles.
+    /// Requests the timer interrupt if TIMA overflows.
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut InterruptController) {
+        for _ in 0..cycles {
+            let input = self.input();
+            self.counter = self.counter.wrapping_add(1);
+            self.falling_edge(input, interrupts);
+        }
+    }
+
+    /// Returns the register at `addr`.
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            DIV => (self.counter >> 8) as u8,
+            TIMA => self.tima,
+            TMA => self.tma,
+            TAC => self.tac | 0xf8,
+            _ => unreachable!("{:#06x} is not a timer register", addr),
+        }
+    }
+
+    /// Writes the register at `addr`.
+    /// Requests the timer interrupt if this overflows TIMA.
+    pub fn write_byte(&mut self, addr: u16, byte: u8, interrupts: &mut InterruptController) {
+        let input = self.input();
+        match addr {
+            // Any write resets the whole counter.
+            DIV => self.counter = 0,
+            TIMA => self.tima = byte,
+            TMA => self.tma = byte,
+            TAC => self.tac = byte & 0x07,
+            _ => unreachable!("{:#06x} is not a timer register", addr),
+        }
+        self.falling_edge(input, interrupts);
+    }
+
+    /// Returns whether TIMA is enabled and the counter bit selected by TAC is set.
+    fn input(&self) -> bool {
+        let bit = TAC_BITS[(self.tac & 0x03) as usize];
+        self.tac & 0x04 != 0 && self.counter & (1 << bit) != 0
+    }
+
+    /// Increments TIMA if the input went from set to unset.
+    /// On overflow, reloads TMA and requests the timer interrupt.
+    fn falling_edge(&mut self, old_input: bool, interrupts: &mut InterruptController) {
+        if !old_input || self.input() {
+            return;
+        }
+        let (tima, overflowed) = self.tima.overflowing_add(1);
+        self.tima = if overflowed { self.tma } else { tima };
+        if overflowed {
+            interrupts.request(Interrupt::Timer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupts::IE;
+
+    /// Returns a timer and the interrupt controller it requests interrupts from,
+    /// with the timer interrupt enabled.
+    fn setup() -> (Timer, InterruptController) {
+        let mut interrupts = InterruptController::new();
+        interrupts.write_byte(IE, Interrupt::Timer.mask());
+        (Timer::new(), interrupts)
+    }
+
+    #[test]
+    fn div_counts_every_256_cycles_and_resets_on_write() {
+        let (mut timer, mut interrupts) = setup();
+        for _ in 0..3 {
+            timer.tick(128, &mut interrupts);
+        }
+        assert_eq!(timer.read_byte(DIV), 1);
+
+        timer.write_byte(DIV, 0xab, &mut interrupts);
+        assert_eq!(timer.read_byte(DIV), 0);
+    }
+
+    #[test]
+    fn tima_counts_at_the_selected_frequency() {
+        // (TAC, T-cycles per increment)
+        for (tac, period) in [(0x04, 1024), (0x05, 16), (0x06, 64), (0x07, 256)] {
+            let (mut timer, mut interrupts) = setup();
+            timer.write_byte(TAC, tac, &mut interrupts);
+            for _ in 0..period / 4 {
+                timer.tick(4, &mut interrupts);
+            }
+            assert_eq!(timer.read_byte(TIMA), 1, "TAC {:#04x}", tac);
+        }
+    }
+
+    #[test]
+    fn tima_is_stopped_when_disabled() {
+        let (mut timer, mut interrupts) = setup();
+        timer.write_byte(TAC, 0x01, &mut interrupts);
+        for _ in 0..64 {
+            timer.tick(4, &mut interrupts);
+        }
+        assert_eq!(timer.read_byte(TIMA), 0);
+    }
+
+    #[test]
+    fn tima_overflow_reloads_tma_and_requests_an_interrupt() {
+        let (mut timer, mut interrupts) = setup();
+        timer.write_byte(TMA, 0x42, &mut interrupts);
+        timer.write_byte(TIMA, 0xff, &mut interrupts);
+        timer.write_byte(TAC, 0x05, &mut interrupts);
+
+        timer.tick(12, &mut interrupts);
+        assert_eq!(interrupts.pending(), None);
+        timer.tick(4, &mut interrupts);
+        assert_eq!(interrupts.pending(), Some(Interrupt::Timer));
+        assert_eq!(timer.read_byte(TIMA), 0x42);
+    }
+
+    #[test]
+    fn resetting_div_on_a_set_input_increments_tima() {
+        let (mut timer, mut interrupts) = setup();
+        timer.write_byte(TAC, 0x05, &mut interrupts);
+        timer.tick(8, &mut interrupts); // Sets bit 3 of the counter.
+
+        timer.write_byte(DIV, 0, &mut interrupts);
+        assert_eq!(timer.read_byte(TIMA), 1);
+    }
+}
@@ -0,0 +1,209 @@
+//! Runs Blargg's test ROMs, https://github.com/retrio/gb-test-roms.
+//!
+//! The ROMs print their results over the serial port, ending with "Passed" or
+//! "Failed". They are not in the repository, so their tests are ignored: point
+//! `BLARGG_TESTS` at a copy of the suite and run `cargo test --test blargg -- --ignored`.
+//! Only the ROMs that fit without a memory bank controller are run, so cpu_instrs
+//! and mem_timing are run one test at a time.
+
+mod common;
+
+use std::env;
+use std::path::PathBuf;
+
+use common::{ Outcome, CLOCK_HZ };
+
+/// The emulated time a ROM may run, as the longest cpu_instrs tests take about a minute.
+const TIMEOUT_CYCLES: u64 = 120 * CLOCK_HZ;
+
+/// The emulated time given to a failed ROM to print the details.
+const FAILED_CYCLES: u64 = CLOCK_HZ;
+
+/// Runs `rom` until it prints its result, or for `timeout` T-cycles.
+/// Returns how it ended along with everything it printed.
+fn run(rom: &[u8], timeout: u64) -> (Outcome, String) {
+    let (mut cpu, mut memory) = common::boot(rom);
+    let mut failed_at = None;
+    let mut printed = 0;
+
+    let outcome = common::run_until(&mut cpu, &mut memory, timeout, |cpu, memory| {
+        let cycles = cpu.state().cycles;
+        // The output is only searched once more of it is printed.
+        if memory.serial_output().len() != printed {
+            printed = memory.serial_output().len();
+            let output = String::from_utf8_lossy(memory.serial_output());
+            if output.contains("Passed") {
+                return Some(Outcome::Passed);
+            }
+            if failed_at.is_none() && output.contains("Failed") {
+                failed_at = Some(cycles);
+            }
+        }
+        match failed_at {
+            Some(start) if cycles - start >= FAILED_CYCLES => Some(Outcome::Failed),
+            _ => None,
+        }
+    });
+    // A ROM failing right before the timeout still failed.
+    let outcome = match outcome {
+        Outcome::TimedOut if failed_at.is_some() => Outcome::Failed,
+        outcome => outcome,
+    };
+    (outcome, String::from_utf8_lossy(memory.serial_output()).into_owned())
+}
+
+/// Returns the path of `rom` in the suite.
+/// Panics if `BLARGG_TESTS` is not set or `rom` is missing, as the suite is then incomplete.
+fn rom_path(rom: &str) -> PathBuf {
+    let dir = env::var_os("BLARGG_TESTS").expect("BLARGG_TESTS is not set");
+    let path = PathBuf::from(dir).join(rom);
+    assert!(path.is_file(), "{} is missing", path.display());
+    path
+}
+
+/// Runs the ROM at `rom` in the suite, asserting it passes.
+fn blargg(rom: &str) {
+    let (outcome, output) = run(&common::read_rom(&rom_path(rom)), TIMEOUT_CYCLES);
+    assert_eq!(outcome, Outcome::Passed, "{}\n{}", rom, output);
+}
+
+/// Returns a ROM printing `message` over serial, then looping forever.
+fn printing_rom(message: &str) -> Vec<u8> {
+    let bytes: Vec<String> = message.bytes().map(|byte| format!("${:02x}", byte)).collect();
+    common::assemble_rom(&format!("
+        ld hl, message
+    next:
+        ld a, [hl+]
+        and a
+        jr z, done
+        ld [$ff01], a
+        ld a, $81
+        ld [$ff02], a
+    wait:
+        ld a, [$ff02]
+        and $80
+        jr nz, wait
+        jr next
+    done:
+        jr done
+    message:
+        db {}, 0
+    ", bytes.join(", ")))
+}
+
+#[test]
+fn detects_a_passing_rom() {
+    let (outcome, output) = run(&printing_rom("cpu_instrs\n\nPassed\n"), CLOCK_HZ);
+    assert_eq!(outcome, Outcome::Passed);
+    assert_eq!(output, "cpu_instrs\n\nPassed");
+}
+
+#[test]
+fn detects_a_failing_rom_and_captures_the_details() {
+    let (outcome, output) = run(&printing_rom("Failed #2\n"), CLOCK_HZ);
+    assert_eq!(outcome, Outcome::Failed);
+    assert_eq!(output, "Failed #2\n");
+}
+
+#[test]
+fn times_out_a_stuck_rom() {
+    let (outcome, output) = run(&printing_rom("01-special\n"), CLOCK_HZ);
+    assert_eq!(outcome, Outcome::TimedOut);
+    assert_eq!(output, "01-special\n");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_01_special() {
+    blargg("cpu_instrs/individual/01-special.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_02_interrupts() {
+    blargg("cpu_instrs/individual/02-interrupts.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_03_op_sp_hl() {
+    blargg("cpu_instrs/individual/03-op sp,hl.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_04_op_r_imm() {
+    blargg("cpu_instrs/individual/04-op r,imm.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_05_op_rp() {
+    blargg("cpu_instrs/individual/05-op rp.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_06_ld_r_r() {
+    blargg("cpu_instrs/individual/06-ld r,r.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_07_jr_jp_call_ret_rst() {
+    blargg("cpu_instrs/individual/07-jr,jp,call,ret,rst.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_08_misc_instrs() {
+    blargg("cpu_instrs/individual/08-misc instrs.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_09_op_r_r() {
+    blargg("cpu_instrs/individual/09-op r,r.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_10_bit_ops() {
+    blargg("cpu_instrs/individual/10-bit ops.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn cpu_instrs_11_op_a_hl() {
+    blargg("cpu_instrs/individual/11-op a,(hl).gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn instr_timing() {
+    blargg("instr_timing/instr_timing.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn mem_timing_01_read_timing() {
+    blargg("mem_timing/individual/01-read_timing.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn mem_timing_02_write_timing() {
+    blargg("mem_timing/individual/02-write_timing.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn mem_timing_03_modify_timing() {
+    blargg("mem_timing/individual/03-modify_timing.gb");
+}
+
+#[test]
+#[ignore = "set BLARGG_TESTS"]
+fn halt_bug() {
+    blargg("halt_bug.gb");
+}
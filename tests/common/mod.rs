@@ -0,0 +1,71 @@
+//! Helpers shared by the test ROM harnesses.
+
+use std::fs;
+use std::path::Path;
+
+use disco_gb::asm;
+use disco_gb::cpu::{ Cpu, Timing };
+use disco_gb::memory::Memory;
+use disco_gb::model::Model;
+
+/// The T-cycles in a second.
+pub const CLOCK_HZ: u64 = 4_194_304;
+
+/// How a test ROM ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    /// The ROM ran for its whole timeout without a result, as if stuck.
+    TimedOut,
+    /// The CPU executed an illegal opcode.
+    Locked,
+}
+
+/// Reads the ROM at `path`.
+pub fn read_rom(path: &Path) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|err| panic!("failed to read {}: {}", path.display(), err))
+}
+
+/// Returns a 32 KiB ROM whose entry point at 0x0100 is the assembled `source`.
+pub fn assemble_rom(source: &str) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    let program = asm::assemble(0x0100, source).unwrap();
+    rom[0x0100..0x0100 + program.len()].copy_from_slice(&program);
+    rom
+}
+
+/// Returns a DMG about to run `rom` from its entry point, with the boot ROM skipped
+/// and every memory access timed.
+/// There is no PPU yet, so LY always reads 0x90, the first line of V-Blank,
+/// for the ROMs that wait for it.
+pub fn boot(rom: &[u8]) -> (Cpu, Memory) {
+    let mut cpu = Cpu::new();
+    let mut memory = Memory::new();
+    memory.load_rom(rom);
+    memory.skip_boot(Model::Dmg);
+    memory.write_byte(0xff44, 0x90);
+    cpu.skip_boot(Model::Dmg, &memory);
+    cpu.set_timing(Timing::MCycle);
+    (cpu, memory)
+}
+
+/// Steps `cpu` until `check` returns how the ROM ended, or for `timeout` T-cycles.
+/// `check` is called after every step.
+pub fn run_until(
+    cpu: &mut Cpu,
+    memory: &mut Memory,
+    timeout: u64,
+    mut check: impl FnMut(&Cpu, &Memory) -> Option<Outcome>,
+) -> Outcome {
+    while cpu.state().cycles < timeout {
+        if let Err(err) = cpu.step(memory) {
+            eprintln!("{}", err);
+            return Outcome::Locked;
+        }
+        if let Some(outcome) = check(cpu, memory) {
+            return outcome;
+        }
+    }
+    Outcome::TimedOut
+}
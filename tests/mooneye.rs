@@ -0,0 +1,198 @@
+//! Runs the Mooneye Test Suite, https://github.com/Gekkio/mooneye-test-suite.
+//!
+//! The ROMs end by executing `LD B, B` with B, C, D, E, H and L set to the
+//! Fibonacci numbers 3, 5, 8, 13, 21 and 34 if they passed, or to 0x42 if they
+//! failed. They are not in the repository, so the suite is ignored: point
+//! `MOONEYE_TESTS` at a directory of built ROMs, such as `acceptance`, to run every
+//! ROM in it and below. The suite doesn't fail on failing ROMs, it prints a summary
+//! to track the accuracy over time: run it with
+//! `cargo test --test mooneye -- --ignored --nocapture`.
+
+mod common;
+
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::rc::Rc;
+
+use disco_gb::cpu::CpuState;
+
+use common::{ Outcome, CLOCK_HZ };
+
+/// The emulated time a ROM may run, as every ROM finishes within a few seconds.
+const TIMEOUT_CYCLES: u64 = 30 * CLOCK_HZ;
+
+/// B, C, D, E, H and L once a ROM passed.
+const PASSED: [u8; 6] = [3, 5, 8, 13, 21, 34];
+
+/// The result of a ROM in the summary: how it ended, or `None` if it was skipped
+/// as it is for another model than the DMG.
+struct Summarised(Option<Outcome>);
+
+impl fmt::Display for Summarised {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self.0 {
+            Some(Outcome::Passed) => "passed",
+            Some(Outcome::Failed) => "FAILED",
+            Some(Outcome::TimedOut) => "TIMED OUT",
+            Some(Outcome::Locked) => "LOCKED UP",
+            None => "skipped",
+        };
+        f.pad(text)
+    }
+}
+
+/// Returns whether the ROM named `stem` runs on the DMG.
+/// Model specific ROMs end with the models they are for, such as `boot_regs-dmgABC`
+/// or `di_timing-GS`, where G is the DMG family, S the SGB, C the CGB and A the AGB.
+fn runs_on_dmg(stem: &str) -> bool {
+    let models = match stem.rsplit_once('-') {
+        Some((_, models)) => models,
+        None => return true,
+    };
+    if models.starts_with("dmgABC") {
+        return true;
+    }
+    if models.chars().all(|model| "GSCA".contains(model)) {
+        return models.contains('G');
+    }
+    !matches!(models, "dmg0" | "mgb" | "sgb" | "sgb2" | "cgb0" | "cgb" | "cgbABCDE" | "agb" | "ags")
+}
+
+/// Runs `rom` until it executes `LD B, B`, or for `timeout` T-cycles.
+fn run(rom: &[u8], timeout: u64) -> Outcome {
+    let (mut cpu, mut memory) = common::boot(rom);
+    let result = Rc::new(Cell::new(None));
+    let hook_result = Rc::clone(&result);
+    cpu.set_debug_hook(Some(Box::new(move |state: &CpuState| {
+        hook_result.set(Some([state.b, state.c, state.d, state.e, state.h, state.l]));
+    })));
+
+    common::run_until(&mut cpu, &mut memory, timeout, |_, _| match result.get() {
+        Some(PASSED) => Some(Outcome::Passed),
+        Some(_) => Some(Outcome::Failed),
+        None => None,
+    })
+}
+
+/// Returns the path of every ROM in `dir` and its subdirectories, sorted.
+fn find_roms(dir: &Path) -> Vec<PathBuf> {
+    let mut roms = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            roms.extend(find_roms(&path));
+        } else if path.extension() == Some("gb".as_ref()) {
+            roms.push(path);
+        }
+    }
+    roms.sort();
+    roms
+}
+
+/// Runs every ROM in `dir`, returning the outcome of each by its path relative to `dir`,
+/// or `None` for the ROMs skipped as they are for another model.
+fn run_dir(dir: &Path) -> Vec<(String, Option<Outcome>)> {
+    find_roms(dir).into_iter()
+        .map(|path| {
+            let name = path.strip_prefix(dir).unwrap().display().to_string();
+            let stem = path.file_stem().unwrap().to_string_lossy();
+            let outcome = if runs_on_dmg(&stem) {
+                Some(run(&common::read_rom(&path), TIMEOUT_CYCLES))
+            } else {
+                None
+            };
+            (name, outcome)
+        })
+        .collect()
+}
+
+/// Formats `results` as a table, followed by the share of the ROMs run that passed.
+fn summary(results: &[(String, Option<Outcome>)]) -> String {
+    let width = results.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let mut table = String::new();
+    for (name, outcome) in results {
+        table += &format!("{:<width$}  {}\n", name, Summarised(*outcome), width = width);
+    }
+
+    let count = |wanted| results.iter().filter(|(_, outcome)| *outcome == wanted).count();
+    let skipped = count(None);
+    let run = results.len() - skipped;
+    let passed = count(Some(Outcome::Passed));
+    let percent = if run == 0 { 0.0 } else { 100.0 * passed as f64 / run as f64 };
+    table += &format!("\n{}/{} passed ({:.1}%), {} skipped\n", passed, run, percent, skipped);
+    table
+}
+
+#[test]
+#[ignore = "set MOONEYE_TESTS"]
+fn mooneye_tests() {
+    let dir = env::var_os("MOONEYE_TESTS").expect("MOONEYE_TESTS is not set");
+    println!("{}", summary(&run_dir(Path::new(&dir))));
+}
+
+#[test]
+fn classifies_the_fibonacci_registers_as_passed() {
+    let rom = common::assemble_rom("
+        ld b, 3
+        ld c, 5
+        ld d, 8
+        ld e, 13
+        ld h, 21
+        ld l, 34
+        ld b, b
+    done:
+        jr done
+    ");
+    assert_eq!(run(&rom, CLOCK_HZ), Outcome::Passed);
+}
+
+#[test]
+fn classifies_other_registers_as_failed() {
+    let rom = common::assemble_rom("
+        ld a, $42
+        ld b, a
+        ld c, a
+        ld d, a
+        ld e, a
+        ld h, a
+        ld l, a
+        ld b, b
+    done:
+        jr done
+    ");
+    assert_eq!(run(&rom, CLOCK_HZ), Outcome::Failed);
+}
+
+#[test]
+fn classifies_stuck_and_locked_up_roms() {
+    assert_eq!(run(&common::assemble_rom("done: jr done"), CLOCK_HZ), Outcome::TimedOut);
+    assert_eq!(run(&common::assemble_rom("db $d3"), CLOCK_HZ), Outcome::Locked);
+}
+
+#[test]
+fn selects_the_roms_for_the_dmg() {
+    for stem in ["add_sp_e_timing", "boot_regs-dmgABC", "di_timing-GS", "boot_hwio-dmgABCmgb"] {
+        assert!(runs_on_dmg(stem), "{}", stem);
+    }
+    for stem in ["boot_div-dmg0", "boot_regs-mgb", "boot_hwio-S", "boot_regs-sgb2", "boot_div-cgbABCDE"] {
+        assert!(!runs_on_dmg(stem), "{}", stem);
+    }
+}
+
+#[test]
+fn summarises_the_outcomes() {
+    let results = [
+        ("timer/div_write.gb".to_string(), Some(Outcome::Passed)),
+        ("boot_div-dmg0.gb".to_string(), None),
+        ("ei_timing.gb".to_string(), Some(Outcome::Failed)),
+    ];
+    assert_eq!(summary(&results), concat!(
+        "timer/div_write.gb  passed\n",
+        "boot_div-dmg0.gb    skipped\n",
+        "ei_timing.gb        FAILED\n",
+        "\n1/2 passed (50.0%), 1 skipped\n",
+    ));
+}
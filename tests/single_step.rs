@@ -0,0 +1,150 @@
+//! Runs the SingleStepTests SM83 JSON test vectors, https://github.com/SingleStepTests/sm83.
+//!
+//! Every file holds the test cases of one opcode, such as `3e.json` or `cb 7c.json`.
+//! Each case gives the CPU state and RAM before and after executing it, and its bus
+//! activity per M-cycle. The cases are run on a flat bus, where every address is RAM.
+//!
+//! The full suite is not in the repository, so its test is ignored: clone it, point
+//! `SM83_TESTS` at its `v1` directory and run `cargo test --test single_step -- --ignored`.
+//! A few hand written cases in `tests/data/sm83` always run.
+
+use std::env;
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use serde_json::Value;
+
+use disco_gb::cpu::registers::{ Reg16, Reg8 };
+use disco_gb::cpu::Cpu;
+use disco_gb::interrupts::IE;
+use disco_gb::memory::Memory;
+
+/// The failures of one test file.
+struct Report {
+    file: String,
+    cases: usize,
+    failures: Vec<String>,
+}
+
+/// Returns the field `key` of the JSON object `state` as a number.
+fn field(state: &Value, key: &str) -> u16 {
+    state[key].as_u64().unwrap_or_else(|| panic!("missing {}", key)) as u16
+}
+
+/// Returns the `[address, byte]` pairs of the RAM of `state`.
+fn ram(state: &Value) -> Vec<(u16, u8)> {
+    state["ram"].as_array().unwrap().iter()
+        .map(|pair| (pair[0].as_u64().unwrap() as u16, pair[1].as_u64().unwrap() as u8))
+        .collect()
+}
+
+/// Sets up a CPU and a flat bus in the `initial` state of a test case.
+///
+/// The vectors model the SM83 fetching the next opcode during the last M-cycle of
+/// an instruction, so their PC is already past the opcode: the CPU starts one
+/// byte earlier, and its PC is one byte behind the expected one at the end.
+fn setup(initial: &Value) -> (Cpu, Memory) {
+    let mut cpu = Cpu::new();
+    for (key, reg) in [("b", Reg8::B), ("c", Reg8::C), ("d", Reg8::D), ("e", Reg8::E), ("h", Reg8::H), ("l", Reg8::L)] {
+        cpu.set_reg8(reg, field(initial, key) as u8);
+    }
+    cpu.set_reg16(Reg16::AF, field(initial, "a") << 8 | field(initial, "f"));
+    cpu.set_reg16(Reg16::SP, field(initial, "sp"));
+    cpu.set_pc(field(initial, "pc").wrapping_sub(1));
+    cpu.set_ime(field(initial, "ime") != 0);
+
+    let mut memory = Memory::flat();
+    memory.write_byte(IE, field(initial, "ie") as u8);
+    for (addr, byte) in ram(initial) {
+        memory.write_byte(addr, byte);
+    }
+    (cpu, memory)
+}
+
+/// Runs the test case `case`, returning how its final state differs from the expected one.
+fn run_case(case: &Value) -> Vec<String> {
+    let (mut cpu, mut memory) = setup(&case["initial"]);
+    let result = cpu.step(&mut memory);
+
+    let expected = &case["final"];
+    let state = cpu.state();
+    let mut mismatches = Vec::new();
+    let mut check = |name: &str, actual: u16, expected: u16| {
+        if actual != expected {
+            mismatches.push(format!("{} is {:#06x}, expected {:#06x}", name, actual, expected));
+        }
+    };
+
+    let registers = [
+        ("a", state.a), ("f", state.f), ("b", state.b), ("c", state.c),
+        ("d", state.d), ("e", state.e), ("h", state.h), ("l", state.l),
+    ];
+    for (key, value) in registers {
+        check(key, value as u16, field(expected, key));
+    }
+    check("sp", state.sp, field(expected, "sp"));
+    check("pc", state.pc.wrapping_add(1), field(expected, "pc"));
+    check("ime", state.ime as u16, field(expected, "ime"));
+    check("ie", memory.read_byte(IE) as u16, field(expected, "ie"));
+    for (addr, byte) in ram(expected) {
+        check(&format!("({:#06x})", addr), memory.read_byte(addr) as u16, byte as u16);
+    }
+
+    let cycles = case["cycles"].as_array().unwrap().len() as u16 * 4;
+    match result {
+        Ok(info) => check("cycles", info.cycles as u16, cycles),
+        Err(err) => mismatches.push(err.to_string()),
+    }
+    mismatches
+}
+
+/// Runs every test case of the file at `path`.
+fn run_file(path: &Path) -> Report {
+    let json = fs::read_to_string(path).unwrap();
+    let cases: Vec<Value> = serde_json::from_str(&json).unwrap();
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let mismatches = run_case(case);
+        if !mismatches.is_empty() {
+            failures.push(format!("{}: {}", case["name"].as_str().unwrap(), mismatches.join(", ")));
+        }
+    }
+
+    let file = path.file_stem().unwrap().to_string_lossy().into_owned();
+    Report { file, cases: cases.len(), failures }
+}
+
+/// Runs every test file in `dir`, panicking with a summary per opcode if any case failed.
+fn run_dir(dir: &Path) {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some("json".as_ref()))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no test files in {}", dir.display());
+
+    let mut summary = String::new();
+    for path in paths {
+        let report = run_file(&path);
+        if let Some(first) = report.failures.first() {
+            summary += &format!(
+                "{}: {}/{} cases failed, first {}\n",
+                report.file, report.failures.len(), report.cases, first,
+            );
+        }
+    }
+    assert!(summary.is_empty(), "\n{}", summary);
+}
+
+#[test]
+fn sample_cases() {
+    run_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/sm83"));
+}
+
+#[test]
+#[ignore = "set SM83_TESTS"]
+fn single_step_tests() {
+    let dir = env::var_os("SM83_TESTS").expect("SM83_TESTS is not set");
+    run_dir(Path::new(&dir));
+}